@@ -0,0 +1,91 @@
+//! Compares the compiled brain evaluation against a straightforward evaluation of the genome
+//! that allocates per tick, the way brains were evaluated before `Network` existed.
+//! Run with `cargo bench --bench brain`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use organisms::brain::{Brain, NodeKind};
+use organisms::innovation::InnovationTracker;
+use organisms::mutation::{MutationPolicy, MutationRates};
+use organisms::senses;
+
+const BRAIN_NUM: usize = 2_000;
+const TICKS: usize = 50;
+
+fn make_brains(rng: &mut StdRng) -> Vec<Brain> {
+    let mut tracker = InnovationTracker::new();
+    let policy = MutationPolicy { add_connection_prob: 1.0, add_node_prob: 1.0, ..MutationPolicy::default() };
+    let rates = MutationRates::default();
+    let keys = senses::input_keys(&[(0, -1), (1, 0), (0, 1)]);
+
+    (0..BRAIN_NUM)
+        .map(|_| {
+            let mut brain = Brain::new(&keys, &mut tracker, rng);
+            for _ in 0..5 {
+                brain = brain.child_brain(&keys, 3, &rates, &policy, &mut tracker, rng);
+            }
+            brain
+        })
+        .collect()
+}
+
+fn naive_evaluate(brain: &Brain, inputs: Vec<f32>, values: &mut [f32]) -> Vec<f32> {
+    let index: HashMap<usize, usize> = brain.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+    let mut outputs = Vec::new();
+    for i in 0..brain.nodes.len() {
+        let node = &brain.nodes[i];
+        values[i] = match node.kind {
+            NodeKind::Input(_, slot) => inputs.get(slot).copied().unwrap_or(0.0),
+            _ => {
+                let sum: f32 = brain.connections
+                    .iter()
+                    .filter(|connection| connection.enabled && connection.to == node.id)
+                    .map(|connection| (connection.weight + connection.learned) * values[index[&connection.from]])
+                    .sum();
+                node.activation.apply(sum)
+            }
+        };
+        if let NodeKind::Output(_) = node.kind {
+            outputs.push(values[i]);
+        }
+    }
+    outputs
+}
+
+fn time(label: &str, mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    f();
+    let elapsed = start.elapsed();
+    let per_eval = elapsed.as_nanos() as f64 / (BRAIN_NUM * TICKS) as f64;
+    println!("{label:>10}: {elapsed:>12.2?} total, {per_eval:>8.1} ns per evaluation");
+    elapsed
+}
+
+fn main() {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut brains = make_brains(&mut rng);
+    let inputs: Vec<Vec<f32>> = (0..TICKS)
+        .map(|_| (0..senses::input_keys(&[(0, -1), (1, 0), (0, 1)]).len()).map(|_| rng.gen_range(0.0..1.0)).collect())
+        .collect();
+
+    let naive = time("naive", || {
+        let mut values: Vec<Vec<f32>> = brains.iter().map(|brain| brain.values.clone()).collect();
+        for tick in inputs.iter() {
+            for (brain, values) in brains.iter().zip(values.iter_mut()) {
+                black_box(naive_evaluate(brain, tick.clone(), values));
+            }
+        }
+    });
+
+    let compiled = time("compiled", || {
+        for tick in inputs.iter() {
+            for brain in brains.iter_mut() {
+                black_box(brain.process_input(tick));
+            }
+        }
+    });
+
+    println!("speedup: {:.1}x", naive.as_secs_f64() / compiled.as_secs_f64());
+}
@@ -1,109 +1,734 @@
-use rand::{Rng, rngs::StdRng};
-use crate::utils::{MUTATION_RATE, HIDDEN_NEURON_NUM};
+use rand::{Rng, rngs::StdRng, seq::{IteratorRandom, SliceRandom}};
+use crate::utils::{HIDDEN_NEURON_NUM, MEMORY_NEURON_NUM, LEARNING_RATE, HEBBIAN_RATE, MAX_LEARNED_WEIGHT};
+use crate::mutation::{MutationPolicy, MutationRates, gaussian};
+use crate::innovation::{InnovationTracker, NodeKey};
+use crate::senses::InputKey;
+use crate::network::Network;
 use crate::Direction;
 
+pub const ACTION_NUM: usize = 8;
+pub const OUTPUT_NUM: usize = ACTION_NUM + 1; // the last output sets movement speed
+const MAX_PRE_ACTIVATION: f32 = 100.0; // keeps unbounded activations finite in recurrent loops with gains above 1
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    Move(Direction, f32), // speed in 0..=1, scaled by the organism's mover count
+    Rotate(bool), // clockwise
+    Stay,
+    ToggleAttack,
+}
+
+impl Action {
+    /// Decodes the brain's outputs: the strongest of the first `ACTION_NUM` picks the action and the last sets the speed of moves.
+    pub fn from_outputs(outputs: &[f32]) -> Action {
+        let speed = (outputs[ACTION_NUM] + 1.0) / 2.0;
+        let max_index = outputs[..ACTION_NUM]
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(idx, _)| idx)
+            .unwrap_or(0);
+
+        match max_index {
+            0 => Action::Move(Direction::Up, speed),
+            1 => Action::Move(Direction::Down, speed),
+            2 => Action::Move(Direction::Left, speed),
+            3 => Action::Move(Direction::Right, speed),
+            4 => Action::Rotate(true),
+            5 => Action::Rotate(false),
+            6 => Action::ToggleAttack,
+            _ => Action::Stay,
+        }
+    }
+}
+
+/// Whether brains learn during life, and whether learned weights are passed on to offspring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlasticityMode {
+    Off, // pure genetic evolution
+    Baldwinian, // learning only helps the learner, children start from the genetic weights
+    Lamarckian, // learned weights are written back into the genome at birth
+}
+
+impl PlasticityMode {
+    /// Position in declaration order, which is how the mode is stored as a setting.
+    pub fn index(self) -> usize {
+        match self {
+            PlasticityMode::Off => 0,
+            PlasticityMode::Baldwinian => 1,
+            PlasticityMode::Lamarckian => 2,
+        }
+    }
+
+    pub fn from_index(index: usize) -> PlasticityMode {
+        match index {
+            0 => PlasticityMode::Off,
+            1 => PlasticityMode::Baldwinian,
+            _ => PlasticityMode::Lamarckian,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Activation {
+    Tanh,
+    Sigmoid,
+    Relu,
+    Identity,
+    Sine,
+    Step,
+}
+
+impl Activation {
+    pub fn apply(self, x: f32) -> f32 {
+        match self {
+            Activation::Tanh => x.tanh(),
+            Activation::Sigmoid => 1.0 / (1.0 + (-x).exp()),
+            Activation::Relu => x.clamp(0.0, MAX_PRE_ACTIVATION),
+            Activation::Identity => x.clamp(-MAX_PRE_ACTIVATION, MAX_PRE_ACTIVATION),
+            Activation::Sine => x.clamp(-MAX_PRE_ACTIVATION, MAX_PRE_ACTIVATION).sin(),
+            Activation::Step => if x > 0.0 { 1.0 } else { 0.0 },
+        }
+    }
+
+    pub fn from_string(s: &str) -> Option<Activation> {
+        match s {
+            "Tanh" => Some(Activation::Tanh),
+            "Sigmoid" => Some(Activation::Sigmoid),
+            "Relu" => Some(Activation::Relu),
+            "Identity" => Some(Activation::Identity),
+            "Sine" => Some(Activation::Sine),
+            "Step" => Some(Activation::Step),
+            _ => None,
+        }
+    }
+
+    pub fn random(rng: &mut StdRng) -> Activation {
+        match rng.gen_range(0..=5) {
+            0 => Activation::Tanh,
+            1 => Activation::Sigmoid,
+            2 => Activation::Relu,
+            3 => Activation::Identity,
+            4 => Activation::Sine,
+            _ => Activation::Step,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NodeKind {
+    Input(InputKey, usize), // key and current position in the sensor vector
+    Hidden,
+    Output(usize),
+}
+
+impl NodeKind {
+    /// `hidden`, `output:<index>` or `input:<slot>:<input key>`, as stored in world snapshots.
+    pub fn encode(&self) -> String {
+        match self {
+            NodeKind::Input(key, slot) => format!("input:{}:{}", slot, key.encode()),
+            NodeKind::Hidden => "hidden".to_owned(),
+            NodeKind::Output(index) => format!("output:{}", index),
+        }
+    }
+
+    pub fn decode(encoded: &str) -> Option<NodeKind> {
+        let (kind, rest) = encoded.split_once(':').unwrap_or((encoded, ""));
+        match kind {
+            "hidden" => Some(NodeKind::Hidden),
+            "output" => rest.parse().ok().filter(|index| *index < OUTPUT_NUM).map(NodeKind::Output),
+            "input" => {
+                let (slot, key) = rest.split_once(':')?;
+                Some(NodeKind::Input(InputKey::decode(key)?, slot.parse().ok()?))
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct NodeGene {
+    pub id: usize,
+    pub kind: NodeKind,
+    pub activation: Activation,
+    pub order: f32, // inputs sit at 0 and outputs at 1, nodes are evaluated in ascending order
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectionGene {
+    pub innovation: usize,
+    pub from: usize,
+    pub to: usize,
+    pub weight: f32,
+    pub enabled: bool,
+    pub learned: f32, // offset picked up during life, the effective weight is `weight + learned`
+}
+
+/// Graph genome in the style of NEAT. A connection whose source is evaluated after its target
+/// reads the source's value from the previous tick, which is how recurrence and memory work.
 #[derive(Clone)]
 pub struct Brain {
-    pub input: Vec<f32>,
-    pub hidden_layers: Vec<Vec<f32>>,
-    pub weights: Vec<Vec<Vec<f32>>>,
-    pub num_hidden_layers: usize
+    pub nodes: Vec<NodeGene>, // kept sorted by `order`
+    pub connections: Vec<ConnectionGene>,
+    pub values: Vec<f32>, // activation of each node from the latest tick, parallel to `nodes`
+    pub learning_rate: f32, // heritable, scales reward-modulated weight changes
+    pub hebbian_rate: f32, // heritable, scales plain co-activation weight changes
+    network: Option<Network>, // compiled on first use, cleared whenever the genome changes
 }
 
 impl Brain {
-    pub fn new(num_inputs: usize, num_hidden_layers: usize, rng: &mut StdRng) -> Self {
-        let mut weights = Vec::new();
-
-        weights.push(
-            (0..HIDDEN_NEURON_NUM)
-                .map(|_| (0..num_inputs).map(|_| rng.gen_range(-1.0..1.0)).collect())
-                .collect()
-        );
-
-        for _ in 1..num_hidden_layers {
-            weights.push(
-                (0..HIDDEN_NEURON_NUM)
-                    .map(|_| (0..HIDDEN_NEURON_NUM).map(|_| rng.gen_range(-1.0..1.0)).collect())
-                    .collect()
-            );
-        }
-
-        weights.push(
-            (0..4)
-                .map(|_| (0..HIDDEN_NEURON_NUM).map(|_| rng.gen_range(-1.0..1.0)).collect())
-                .collect()
-        );
-
-        Self {
-            input: vec![0.0; num_inputs],
-            hidden_layers: vec![vec![0.0; HIDDEN_NEURON_NUM]; num_hidden_layers],
-            weights,
-            num_hidden_layers
-        }
-    }
-
-    pub fn process_input(&mut self, eye_data: Vec<f32>) -> Direction {
-        self.input = eye_data;
-
-        let mut layer_input = self.input.clone();
-        for (layer_idx, layer_weights) in self.weights.iter().enumerate() {
-            let mut new_layer = vec![0.0; layer_weights.len()];
-            for (neuron_idx, neuron_weights) in layer_weights.iter().enumerate() {
-                let sum: f32 = neuron_weights.iter().zip(&layer_input).map(|(w, v)| w * v).sum();
-                new_layer[neuron_idx] = sum.tanh();
+    /// Minimal starting topology: inputs wired straight to outputs, plus self-recurrent memory neurons.
+    pub fn new(input_keys: &[InputKey], tracker: &mut InnovationTracker, rng: &mut StdRng) -> Self {
+        let mut brain = Brain { nodes: Vec::new(), connections: Vec::new(), values: Vec::new(), learning_rate: LEARNING_RATE, hebbian_rate: HEBBIAN_RATE, network: None };
+
+        let inputs: Vec<usize> = input_keys
+            .iter()
+            .enumerate()
+            .map(|(slot, key)| brain.push_node(tracker.fixed_node(NodeKey::Input(*key)), NodeKind::Input(*key, slot), Activation::Identity, 0.0))
+            .collect();
+        let outputs: Vec<usize> = (0..OUTPUT_NUM)
+            .map(|i| brain.push_node(tracker.fixed_node(NodeKey::Output(i)), NodeKind::Output(i), Activation::Tanh, 1.0))
+            .collect();
+        let memories: Vec<usize> = (0..MEMORY_NEURON_NUM)
+            .map(|i| brain.push_node(tracker.fixed_node(NodeKey::Memory(i)), NodeKind::Hidden, Activation::Tanh, 0.5))
+            .collect();
+
+        for &from in inputs.iter() {
+            for &to in outputs.iter().chain(memories.iter()) {
+                brain.connect(from, to, rng.gen_range(-1.0..1.0), tracker);
             }
-            layer_input = new_layer.clone();
-            if layer_idx < self.hidden_layers.len() {
-                self.hidden_layers[layer_idx] = new_layer;
+        }
+        for &memory in memories.iter() {
+            brain.connect(memory, memory, rng.gen_range(-1.0..1.0), tracker);
+            for &to in outputs.iter() {
+                brain.connect(memory, to, rng.gen_range(-1.0..1.0), tracker);
             }
         }
 
-        let max_index = layer_input
-            .iter()
-            .enumerate()
-            .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
-            .map(|(idx, _)| idx)
-            .unwrap_or(4);
+        brain
+    }
 
-        match max_index {
-            0 => Direction::Up,
-            1 => Direction::Down,
-            2 => Direction::Left,
-            3 => Direction::Right,
-            _ => Direction::None,
+    /// The genome and current activations as tab separated `brain`, `node` and `link` lines for world snapshots.
+    pub fn encode(&self) -> String {
+        let mut lines = vec![format!("brain\t{}\t{}", self.learning_rate, self.hebbian_rate)];
+        for (node, value) in self.nodes.iter().zip(self.values.iter()) {
+            lines.push(format!("node\t{}\t{}\t{:?}\t{}\t{}", node.id, node.kind.encode(), node.activation, node.order, value));
+        }
+        for connection in self.connections.iter() {
+            lines.push(format!("link\t{}\t{}\t{}\t{}\t{}\t{}", connection.innovation, connection.from, connection.to,
+                connection.weight, connection.enabled, connection.learned));
+        }
+        lines.join("\n") + "\n"
+    }
+
+    /// An empty brain from a `brain` line, to be filled by `decode_line`.
+    pub fn decode(fields: &[&str]) -> Option<Brain> {
+        let ["brain", learning_rate, hebbian_rate] = fields else {
+            return None;
+        };
+        Some(Brain { nodes: Vec::new(), connections: Vec::new(), values: Vec::new(), learning_rate: learning_rate.parse().ok()?, hebbian_rate: hebbian_rate.parse().ok()?, network: None })
+    }
+
+    /// Adds the node or connection of a `node` or `link` line. Nodes are expected in the order `encode` writes them,
+    /// so a link between nodes that were not decoded before it is refused, as is an output past `OUTPUT_NUM`.
+    pub fn decode_line(&mut self, fields: &[&str]) -> Option<()> {
+        match fields {
+            ["node", id, kind, activation, order, value] => {
+                self.nodes.push(NodeGene { id: id.parse().ok()?, kind: NodeKind::decode(kind)?, activation: Activation::from_string(activation)?, order: order.parse().ok()? });
+                self.values.push(value.parse().ok()?);
+            }
+            ["link", innovation, from, to, weight, enabled, learned] => {
+                let (from, to) = (from.parse().ok()?, to.parse().ok()?);
+                self.node(from)?;
+                self.node(to)?;
+                self.connections.push(ConnectionGene { innovation: innovation.parse().ok()?, from, to,
+                    weight: weight.parse().ok()?, enabled: enabled.parse().ok()?, learned: learned.parse().ok()? });
+            }
+            _ => return None,
         }
+        self.network = None;
+        Some(())
+    }
+
+    fn push_node(&mut self, id: usize, kind: NodeKind, activation: Activation, order: f32) -> usize {
+        let index = self.nodes.partition_point(|node| node.order <= order);
+        self.nodes.insert(index, NodeGene { id, kind, activation, order });
+        self.values.insert(index, 0.0);
+        id
+    }
+
+    fn connect(&mut self, from: usize, to: usize, weight: f32, tracker: &mut InnovationTracker) {
+        let innovation = tracker.connection(from, to);
+        self.connections.push(ConnectionGene { innovation, from, to, weight, enabled: true, learned: 0.0 });
     }
 
-    pub fn mutate(&mut self, rng: &mut StdRng, brain_num: usize) {
-        if rng.gen::<f32>() < MUTATION_RATE {
-            for layer in self.weights.iter_mut() {
-                for neuron in layer.iter_mut() {
-                    for weight in neuron.iter_mut() {
-                        *weight += rng.gen_range(-MUTATION_RATE..MUTATION_RATE);
-                    }
+    fn node(&self, id: usize) -> Option<&NodeGene> {
+        self.nodes.iter().find(|node| node.id == id)
+    }
+
+    pub fn hidden_num(&self) -> usize {
+        self.nodes.iter().filter(|node| node.kind == NodeKind::Hidden).count()
+    }
+
+    /// Each brain cell allows `HIDDEN_NEURON_NUM` hidden neurons on top of the memory neurons.
+    fn hidden_capacity(brain_num: usize) -> usize {
+        MEMORY_NEURON_NUM + brain_num * HIDDEN_NEURON_NUM
+    }
+
+    pub fn process_input(&mut self, inputs: &[f32]) -> Action {
+        let network = self.network.get_or_insert_with(|| Network::compile(&self.nodes, &self.connections));
+        network.evaluate(inputs, &mut self.values);
+
+        let mut outputs = [0.0; OUTPUT_NUM];
+        for (output, value) in outputs.iter_mut().enumerate() {
+            *value = network.output(&self.values, output);
+        }
+
+        Action::from_outputs(&outputs)
+    }
+
+    /// Hebbian update from the activations of the latest tick. `reward` modulates the learning rate term,
+    /// so co-active connections are strengthened after eating and weakened after taking damage.
+    pub fn learn(&mut self, reward: f32) {
+        let rate = self.hebbian_rate + self.learning_rate * reward;
+        let network = self.network.get_or_insert_with(|| Network::compile(&self.nodes, &self.connections));
+        network.learn(&mut self.connections, &self.values, rate, MAX_LEARNED_WEIGHT);
+    }
+
+    pub fn mutate(&mut self, rng: &mut StdRng, brain_num: usize, rates: &MutationRates, policy: &MutationPolicy, tracker: &mut InnovationTracker) {
+        for connection in self.connections.iter_mut() {
+            rates.mutate_weight(&mut connection.weight, rng);
+        }
+        // plasticity genes are rates, so they take log-normal steps like the mutation rates and stay positive
+        self.learning_rate *= (policy.rate_adaptation * gaussian(rng)).exp();
+        self.hebbian_rate *= (policy.rate_adaptation * gaussian(rng)).exp();
+
+        if rng.gen::<f32>() < policy.add_connection_prob {
+            self.add_connection(tracker, rng);
+        }
+        if rng.gen::<f32>() < policy.add_node_prob && self.hidden_num() < Brain::hidden_capacity(brain_num) {
+            self.add_node(tracker, rng);
+        }
+        if rng.gen::<f32>() < policy.disable_connection_prob {
+            if let Some(connection) = self.connections.iter_mut().filter(|connection| connection.enabled).choose(rng) {
+                connection.enabled = false;
+            }
+        }
+        if rng.gen::<f32>() < policy.activation_prob {
+            if let Some(node) = self.nodes.iter_mut().filter(|node| node.kind == NodeKind::Hidden).choose(rng) {
+                node.activation = Activation::random(rng);
+            }
+        }
+
+        // Losing brain cells prunes hidden neurons down to the new capacity
+        while self.hidden_num() > Brain::hidden_capacity(brain_num) {
+            let hidden: Vec<usize> = self.nodes.iter().filter(|node| node.kind == NodeKind::Hidden).map(|node| node.id).collect();
+            self.remove_node(*hidden.choose(rng).unwrap());
+        }
+        self.network = None;
+    }
+
+    fn add_connection(&mut self, tracker: &mut InnovationTracker, rng: &mut StdRng) {
+        for _ in 0..20 {
+            let from = self.nodes.choose(rng).unwrap().id;
+            let to = self.nodes.choose(rng).unwrap();
+            if let NodeKind::Input(..) = to.kind {
+                continue;
+            }
+            let to = to.id;
+
+            match self.connections.iter_mut().find(|connection| connection.from == from && connection.to == to) {
+                Some(connection) if !connection.enabled => {
+                    connection.enabled = true;
+                    return;
+                }
+                Some(_) => continue,
+                None => {
+                    self.connect(from, to, rng.gen_range(-1.0..1.0), tracker);
+                    return;
                 }
             }
         }
+    }
+
+    /// Splits an enabled connection in two around a new hidden neuron, keeping the old behaviour close to intact.
+    fn add_node(&mut self, tracker: &mut InnovationTracker, rng: &mut StdRng) {
+        let Some(split) = self.connections.iter_mut().filter(|connection| connection.enabled).choose(rng) else {
+            return;
+        };
+        split.enabled = false;
+        let (innovation, from, to, weight) = (split.innovation, split.from, split.to, split.weight);
+
+        let id = tracker.split(innovation, |id| self.node(id).is_some());
+        let order = match (self.node(from), self.node(to)) {
+            (Some(from), Some(to)) => ((from.order + to.order) / 2.0).clamp(0.01, 0.99),
+            _ => 0.5,
+        };
+
+        self.push_node(id, NodeKind::Hidden, Activation::Tanh, order);
+        self.connect(from, id, 1.0, tracker);
+        self.connect(id, to, weight, tracker);
+    }
+
+    /// Matches the input neurons to a new sensor layout after the anatomy changed. Inputs of lost eyes are
+    /// dropped with their connections, new eyes get fresh inputs wired to every output and memory neuron, and the rest keep their weights.
+    pub fn adapt_inputs(&mut self, input_keys: &[InputKey], tracker: &mut InnovationTracker, rng: &mut StdRng) {
+        let stale: Vec<usize> = self.nodes
+            .iter()
+            .filter(|node| matches!(node.kind, NodeKind::Input(key, _) if !input_keys.contains(&key)))
+            .map(|node| node.id)
+            .collect();
+        for id in stale {
+            self.remove_node(id);
+        }
+
+        // new inputs are wired like in `Brain::new`, to every output and memory neuron
+        let mut targets: Vec<usize> = self.nodes.iter().filter(|node| matches!(node.kind, NodeKind::Output(_))).map(|node| node.id).collect();
+        targets.extend((0..MEMORY_NEURON_NUM).map(|i| tracker.fixed_node(NodeKey::Memory(i))).filter(|id| self.node(*id).is_some()));
+        for (slot, key) in input_keys.iter().enumerate() {
+            if let Some(node) = self.nodes.iter_mut().find(|node| matches!(node.kind, NodeKind::Input(existing, _) if existing == *key)) {
+                node.kind = NodeKind::Input(*key, slot);
+                continue;
+            }
 
-        if brain_num != self.num_hidden_layers {
-            if brain_num > self.num_hidden_layers {
-                let new_layer: Vec<Vec<f32>> = (0..HIDDEN_NEURON_NUM)
-                    .map(|_| (0..HIDDEN_NEURON_NUM).map(|_| rng.gen_range(-1.0..1.0)).collect())
-                    .collect();
-                self.weights.insert(1, new_layer);
-                self.hidden_layers.push(vec![0.0; HIDDEN_NEURON_NUM]);
-                self.num_hidden_layers += 1;
-            } else {
-                self.weights.pop();
-                self.hidden_layers.pop();
-                self.num_hidden_layers -= 1;
+            let id = self.push_node(tracker.fixed_node(NodeKey::Input(*key)), NodeKind::Input(*key, slot), Activation::Identity, 0.0);
+            for &to in targets.iter() {
+                self.connect(id, to, rng.gen_range(-1.0..1.0), tracker);
             }
         }
+        self.network = None;
     }
 
-    pub fn child_brain(&self, brain_num: usize, rng: &mut StdRng) -> Brain {
+    fn remove_node(&mut self, id: usize) {
+        if let Some(index) = self.nodes.iter().position(|node| node.id == id) {
+            self.nodes.remove(index);
+            self.values.remove(index);
+        }
+        self.connections.retain(|connection| connection.from != id && connection.to != id);
+    }
+
+    pub fn child_brain(&self, input_keys: &[InputKey], brain_num: usize, rates: &MutationRates, policy: &MutationPolicy, tracker: &mut InnovationTracker, rng: &mut StdRng) -> Brain {
         let mut new_brain: Brain = self.clone();
-        new_brain.mutate(rng, brain_num);
+        new_brain.values.fill(0.0);
+        for connection in new_brain.connections.iter_mut() {
+            if policy.plasticity == PlasticityMode::Lamarckian {
+                connection.weight += connection.learned;
+            }
+            connection.learned = 0.0;
+        }
+        new_brain.network = None;
+        new_brain.adapt_inputs(input_keys, tracker, rng);
+        new_brain.mutate(rng, brain_num, rates, policy, tracker);
         new_brain
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+    use crate::senses::input_keys;
+
+    fn outputs(strongest: usize, speed: f32) -> [f32; OUTPUT_NUM] {
+        let mut outputs = [-0.5; OUTPUT_NUM];
+        outputs[strongest] = 0.5;
+        outputs[ACTION_NUM] = speed;
+        outputs
+    }
+
+    #[test]
+    fn each_output_decodes_to_its_action() {
+        let expected = [
+            Action::Move(Direction::Up, 0.75),
+            Action::Move(Direction::Down, 0.75),
+            Action::Move(Direction::Left, 0.75),
+            Action::Move(Direction::Right, 0.75),
+            Action::Rotate(true),
+            Action::Rotate(false),
+            Action::ToggleAttack,
+            Action::Stay,
+        ];
+        for (index, action) in expected.iter().enumerate() {
+            assert_eq!(Action::from_outputs(&outputs(index, 0.5)), *action);
+        }
+    }
+
+    #[test]
+    fn speed_output_maps_onto_zero_to_one() {
+        assert_eq!(Action::from_outputs(&outputs(0, -1.0)), Action::Move(Direction::Up, 0.0));
+        assert_eq!(Action::from_outputs(&outputs(0, 0.0)), Action::Move(Direction::Up, 0.5));
+        assert_eq!(Action::from_outputs(&outputs(0, 1.0)), Action::Move(Direction::Up, 1.0));
+    }
+
+    fn memory_values(brain: &Brain) -> Vec<f32> {
+        brain.nodes.iter().zip(brain.values.iter()).filter(|(node, _)| node.kind == NodeKind::Hidden).map(|(_, value)| *value).collect()
+    }
+
+    fn assert_valid(brain: &Brain) {
+        assert_eq!(brain.values.len(), brain.nodes.len());
+        assert!(brain.nodes.windows(2).all(|pair| pair[0].order <= pair[1].order), "nodes stay sorted by order");
+        for connection in brain.connections.iter() {
+            assert!(brain.node(connection.from).is_some(), "connection from a missing node");
+            let to = brain.node(connection.to).expect("connection into a missing node");
+            assert!(!matches!(to.kind, NodeKind::Input(..)), "connection into an input");
+        }
+    }
+
+    #[test]
+    fn memory_keeps_the_last_tick_and_feeds_it_back() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut brain = Brain::new(&input_keys(&[]), &mut InnovationTracker::new(), &mut rng);
+        brain.process_input(&[1.0, -1.0]);
+        assert_eq!(memory_values(&brain).len(), MEMORY_NEURON_NUM);
+        assert!(memory_values(&brain).iter().any(|value| *value != 0.0));
+
+        let mut forgetful = brain.clone();
+        forgetful.values.fill(0.0);
+        brain.process_input(&[0.0, 0.0]);
+        forgetful.process_input(&[0.0, 0.0]);
+        assert!(forgetful.values.iter().all(|value| *value == 0.0), "without memory a silent input leaves the brain silent");
+        assert!(memory_values(&brain).iter().any(|value| *value != 0.0), "the remembered tick still drives the brain");
+    }
+
+    #[test]
+    fn children_start_with_empty_memory() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut tracker = InnovationTracker::new();
+        let mut brain = Brain::new(&input_keys(&[]), &mut tracker, &mut rng);
+        brain.process_input(&[1.0, 1.0]);
+        let child = brain.child_brain(&input_keys(&[]), 1, &MutationRates::default(), &MutationPolicy::default(), &mut tracker, &mut rng);
+        assert!(child.values.iter().all(|value| *value == 0.0));
+    }
+
+    #[test]
+    fn tracker_reuses_numbers_for_the_same_mutation() {
+        let mut tracker = InnovationTracker::new();
+        assert_eq!(tracker.fixed_node(NodeKey::Input(InputKey::Internal(0))), tracker.fixed_node(NodeKey::Input(InputKey::Internal(0))));
+        assert_ne!(tracker.fixed_node(NodeKey::Input(InputKey::Internal(0))), tracker.fixed_node(NodeKey::Output(0)));
+
+        let first = tracker.connection(0, 1);
+        assert_eq!(tracker.connection(0, 1), first);
+        assert_ne!(tracker.connection(1, 0), first);
+
+        let split = tracker.split(first, |_| false);
+        assert_eq!(tracker.split(first, |_| false), split, "the same split in another genome gets the same node");
+        assert_ne!(tracker.split(first, |id| id == split), split, "a genome that already owns the node gets a fresh one");
+    }
+
+    fn decode_lines<T>(encoded: &str, mut decode: impl FnMut(&[&str]) -> Option<T>) -> Option<()> {
+        for line in encoded.lines() {
+            decode(&line.split('\t').collect::<Vec<&str>>())?;
+        }
+        Some(())
+    }
+
+    #[test]
+    fn tracker_round_trips_through_its_lines() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut tracker = InnovationTracker::new();
+        let mut brain = Brain::new(&input_keys(&[(0, -1)]), &mut tracker, &mut rng);
+        let policy = MutationPolicy { add_node_prob: 1.0, add_connection_prob: 1.0, ..MutationPolicy::default() };
+        for _ in 0..5 {
+            brain.mutate(&mut rng, 2, &MutationRates::default(), &policy, &mut tracker);
+        }
+
+        let mut decoded = InnovationTracker::new();
+        decode_lines(&tracker.encode(), |fields| decoded.decode_line(fields)).unwrap();
+        assert_eq!(decoded.encode(), tracker.encode());
+        let first = &brain.connections[0];
+        assert_eq!(decoded.connection(first.from, first.to), first.innovation, "known connections keep their number");
+        assert_eq!(decoded.connection(1000, 1001), tracker.connection(1000, 1001), "new numbers continue where the tracker stopped");
+        assert_eq!(decoded.split(first.innovation, |_| false), tracker.split(first.innovation, |_| false));
+        assert_eq!(decoded.fixed_node(NodeKey::Output(0)), tracker.fixed_node(NodeKey::Output(0)));
+    }
+
+    #[test]
+    fn node_keys_round_trip_and_refuse_indices_outside_the_layout() {
+        for key in [NodeKey::Input(InputKey::Eye { x: -1, y: 2, channel: 3 }), NodeKey::Output(OUTPUT_NUM - 1), NodeKey::Memory(MEMORY_NEURON_NUM - 1)] {
+            assert_eq!(NodeKey::decode(&key.encode()), Some(key));
+        }
+        assert_eq!(NodeKey::decode(&NodeKey::Output(OUTPUT_NUM).encode()), None);
+        assert_eq!(NodeKey::decode(&NodeKey::Memory(MEMORY_NEURON_NUM).encode()), None);
+        assert_eq!(NodeKind::decode(&NodeKind::Output(OUTPUT_NUM).encode()), None);
+    }
+
+    #[test]
+    fn brain_round_trips_through_its_lines() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut tracker = InnovationTracker::new();
+        let mut brain = Brain::new(&input_keys(&[(1, 0)]), &mut tracker, &mut rng);
+        brain.mutate(&mut rng, 2, &MutationRates::default(), &MutationPolicy { add_node_prob: 1.0, ..MutationPolicy::default() }, &mut tracker);
+        let inputs = vec![0.25; input_keys(&[(1, 0)]).len()];
+        brain.process_input(&inputs);
+
+        let encoded = brain.encode();
+        let mut lines = encoded.lines().map(|line| line.split('\t').collect::<Vec<&str>>());
+        let mut decoded = Brain::decode(&lines.next().unwrap()).unwrap();
+        for fields in lines {
+            decoded.decode_line(&fields).unwrap();
+        }
+        assert_eq!(decoded.encode(), encoded);
+        assert_eq!(decoded.process_input(&inputs), brain.process_input(&inputs));
+    }
+
+    #[test]
+    fn brain_lines_with_unknown_outputs_or_dangling_links_are_refused() {
+        let mut brain = Brain::decode(&["brain", "0.1", "0.1"]).unwrap();
+        assert!(brain.decode_line(&["node", "0", &format!("output:{}", OUTPUT_NUM), "Tanh", "1", "0"]).is_none());
+        brain.decode_line(&["node", "0", "output:0", "Tanh", "1", "0"]).unwrap();
+        assert!(brain.decode_line(&["link", "0", "7", "0", "0.5", "true", "0"]).is_none());
+        assert!(brain.decode_line(&["link", "0", "0", "7", "0.5", "true", "0"]).is_none());
+        assert!(brain.connections.is_empty());
+    }
+
+    #[test]
+    fn structural_mutations_keep_the_graph_valid() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut tracker = InnovationTracker::new();
+        let policy = MutationPolicy { add_connection_prob: 1.0, add_node_prob: 1.0, disable_connection_prob: 0.5, activation_prob: 0.5, ..MutationPolicy::default() };
+        let mut brain = Brain::new(&input_keys(&[(0, -1)]), &mut tracker, &mut rng);
+        let connections = brain.connections.len();
+        for _ in 0..50 {
+            brain.mutate(&mut rng, 10, &MutationRates::default(), &policy, &mut tracker);
+            assert_valid(&brain);
+            brain.process_input(&[0.5, -0.5, 1.0]);
+        }
+        assert!(brain.hidden_num() > MEMORY_NEURON_NUM);
+        assert!(brain.connections.len() > connections);
+        assert!(brain.values.iter().all(|value| value.is_finite()));
+    }
+
+    #[test]
+    fn losing_brain_cells_prunes_hidden_neurons() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut tracker = InnovationTracker::new();
+        let grow = MutationPolicy { add_node_prob: 1.0, ..MutationPolicy::default() };
+        let mut brain = Brain::new(&input_keys(&[]), &mut tracker, &mut rng);
+        for _ in 0..3 * HIDDEN_NEURON_NUM {
+            brain.mutate(&mut rng, 3, &MutationRates::default(), &grow, &mut tracker);
+        }
+        assert_eq!(brain.hidden_num(), Brain::hidden_capacity(3), "growth stops at the capacity");
+
+        let still = MutationPolicy { add_node_prob: 0.0, ..MutationPolicy::default() };
+        brain.mutate(&mut rng, 1, &MutationRates::default(), &still, &mut tracker);
+        assert_eq!(brain.hidden_num(), Brain::hidden_capacity(1));
+        assert_valid(&brain);
+    }
+
+    fn eye_inputs(brain: &Brain, x: i32, y: i32) -> Vec<usize> {
+        brain.nodes.iter().filter(|node| matches!(node.kind, NodeKind::Input(InputKey::Eye { x: eye_x, y: eye_y, .. }, _) if (eye_x, eye_y) == (x, y))).map(|node| node.id).collect()
+    }
+
+    #[test]
+    fn adapt_inputs_keeps_the_weights_of_unchanged_eyes() {
+        let mut tracker = InnovationTracker::new();
+        let mut rng = StdRng::seed_from_u64(1);
+        let brain = Brain::new(&input_keys(&[(0, -1), (1, 0)]), &mut tracker, &mut rng);
+        let mut adapted = brain.clone();
+        adapted.adapt_inputs(&input_keys(&[(1, 0)]), &mut tracker, &mut rng);
+
+        let kept = eye_inputs(&brain, 1, 0);
+        for connection in brain.connections.iter().filter(|connection| kept.contains(&connection.from)) {
+            let same = adapted.connections.iter().find(|other| other.innovation == connection.innovation).expect("connection of a kept eye was dropped");
+            assert_eq!(same.weight, connection.weight);
+        }
+        // the kept eye moved up to the slots right after the internal inputs
+        let slots: Vec<usize> = adapted.nodes.iter().filter_map(|node| match node.kind { NodeKind::Input(_, slot) => Some(slot), _ => None }).collect();
+        assert_eq!(slots.iter().max(), Some(&(input_keys(&[(1, 0)]).len() - 1)));
+    }
+
+    #[test]
+    fn adapt_inputs_drops_the_inputs_of_lost_eyes() {
+        let mut tracker = InnovationTracker::new();
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut brain = Brain::new(&input_keys(&[(0, -1), (1, 0)]), &mut tracker, &mut rng);
+        let lost = eye_inputs(&brain, 0, -1);
+        brain.adapt_inputs(&input_keys(&[(1, 0)]), &mut tracker, &mut rng);
+
+        assert!(eye_inputs(&brain, 0, -1).is_empty());
+        assert!(brain.connections.iter().all(|connection| !lost.contains(&connection.from)));
+        assert_eq!(brain.nodes.len(), brain.values.len());
+    }
+
+    #[test]
+    fn adapt_inputs_wires_new_eyes_to_outputs_and_memory() {
+        let mut tracker = InnovationTracker::new();
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut brain = Brain::new(&input_keys(&[(0, -1)]), &mut tracker, &mut rng);
+        brain.adapt_inputs(&input_keys(&[(0, -1), (1, 0)]), &mut tracker, &mut rng);
+
+        let mut targets: Vec<usize> = (0..OUTPUT_NUM).map(|i| tracker.fixed_node(NodeKey::Output(i))).collect();
+        targets.extend((0..MEMORY_NEURON_NUM).map(|i| tracker.fixed_node(NodeKey::Memory(i))));
+        let added = eye_inputs(&brain, 1, 0);
+        assert!(!added.is_empty());
+        for from in added {
+            for &to in targets.iter() {
+                assert!(brain.connections.iter().any(|connection| connection.from == from && connection.to == to && connection.enabled),
+                    "input {} is not wired to {}", from, to);
+            }
+        }
+    }
+
+    #[test]
+    fn learning_follows_co_activation_scaled_by_reward() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut brain = Brain::new(&input_keys(&[]), &mut InnovationTracker::new(), &mut rng);
+        brain.learning_rate = 0.2;
+        brain.hebbian_rate = 0.1;
+        for (i, value) in brain.values.iter_mut().enumerate() {
+            *value = if i % 2 == 0 { 0.5 } else { -1.0 };
+        }
+        let activity: Vec<f32> = brain.connections.iter().map(|connection| {
+            let value = |id| brain.values[brain.nodes.iter().position(|node| node.id == id).unwrap()];
+            value(connection.from) * value(connection.to)
+        }).collect();
+
+        brain.learn(1.0);
+        for (connection, activity) in brain.connections.iter().zip(activity.iter()) {
+            assert!((connection.learned - 0.3 * activity).abs() < 1e-6);
+        }
+        brain.learn(-2.0);
+        for connection in brain.connections.iter() {
+            assert!(connection.learned.abs() < 1e-6, "damage undoes what eating taught");
+        }
+        for _ in 0..1000 {
+            brain.learn(1.0);
+        }
+        assert!(brain.connections.iter().all(|connection| connection.learned.abs() <= MAX_LEARNED_WEIGHT));
+    }
+
+    #[test]
+    fn only_lamarckian_children_inherit_learned_weights() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let mut tracker = InnovationTracker::new();
+        let keys = input_keys(&[]);
+        let mut brain = Brain::new(&keys, &mut tracker, &mut rng);
+        for (i, connection) in brain.connections.iter_mut().enumerate() {
+            connection.learned = i as f32 * 0.01;
+        }
+        let rates = MutationRates { weight_prob: 0.0, ..MutationRates::default() };
+        let still = MutationPolicy { add_connection_prob: 0.0, add_node_prob: 0.0, disable_connection_prob: 0.0, activation_prob: 0.0, ..MutationPolicy::default() };
+
+        let baldwinian = MutationPolicy { plasticity: PlasticityMode::Baldwinian, ..still.clone() };
+        let child = brain.child_brain(&keys, 1, &rates, &baldwinian, &mut tracker, &mut rng);
+        for (parent, child) in brain.connections.iter().zip(child.connections.iter()) {
+            assert_eq!(child.weight, parent.weight);
+            assert_eq!(child.learned, 0.0);
+        }
+
+        let lamarckian = MutationPolicy { plasticity: PlasticityMode::Lamarckian, ..still };
+        let child = brain.child_brain(&keys, 1, &rates, &lamarckian, &mut tracker, &mut rng);
+        for (parent, child) in brain.connections.iter().zip(child.connections.iter()) {
+            assert_eq!(child.weight, parent.weight + parent.learned);
+            assert_eq!(child.learned, 0.0);
+        }
+    }
+
+    #[test]
+    fn plasticity_modes_round_trip_through_their_index() {
+        for mode in [PlasticityMode::Off, PlasticityMode::Baldwinian, PlasticityMode::Lamarckian] {
+            assert_eq!(PlasticityMode::from_index(mode.index()), mode);
+        }
+        assert_eq!(PlasticityMode::from_index(7), PlasticityMode::Lamarckian, "out of range indices pick the last mode");
+    }
+}
@@ -0,0 +1,60 @@
+use std::collections::{BTreeMap, HashMap};
+use macroquad::prelude::*;
+use crate::brain::{Brain, NodeKind};
+
+const NODE_RADIUS: f32 = 4.0;
+const PADDING: f32 = 12.0;
+
+/// Draws the brain's graph inside the given rectangle. Columns follow each node's evaluation order, nodes are
+/// shaded by their current activation (green positive, red negative) and edges are blue for positive and
+/// orange for negative weights, thicker the stronger they are. Recurrent edges are drawn faded.
+pub fn draw_brain(brain: &Brain, x: f32, y: f32, width: f32, height: f32) {
+    draw_rectangle(x, y, width, height, Color::new(0.12, 0.12, 0.14, 1.0));
+
+    let mut columns: BTreeMap<i32, Vec<usize>> = BTreeMap::new();
+    for (i, node) in brain.nodes.iter().enumerate() {
+        columns.entry((node.order * 1000.0).round() as i32).or_default().push(i);
+    }
+
+    let inner_width = width - 2.0 * PADDING;
+    let inner_height = height - 2.0 * PADDING;
+    let mut positions: HashMap<usize, (Vec2, usize)> = HashMap::new(); // node id -> (position, node index)
+    for (column, nodes) in columns.iter() {
+        let node_x = x + PADDING + inner_width * (*column as f32 / 1000.0);
+        let spacing = inner_height / nodes.len() as f32;
+        for (row, &i) in nodes.iter().enumerate() {
+            let node_y = y + PADDING + spacing * (row as f32 + 0.5);
+            positions.insert(brain.nodes[i].id, (vec2(node_x, node_y), i));
+        }
+    }
+
+    for connection in brain.connections.iter().filter(|connection| connection.enabled) {
+        let (Some(&(from, from_index)), Some(&(to, to_index))) = (positions.get(&connection.from), positions.get(&connection.to)) else {
+            continue;
+        };
+        let weight = connection.weight + connection.learned;
+        let recurrent = from_index >= to_index;
+        let alpha = if recurrent { 0.25 } else { 0.7 };
+        let color = if weight >= 0.0 { Color::new(0.3, 0.6, 1.0, alpha) } else { Color::new(1.0, 0.6, 0.2, alpha) };
+        let thickness = weight.abs().clamp(0.2, 3.0);
+
+        if from_index == to_index {
+            draw_circle_lines(from.x, from.y - NODE_RADIUS * 2.0, NODE_RADIUS * 1.5, thickness, color);
+        } else {
+            draw_line(from.x, from.y, to.x, to.y, thickness, color);
+        }
+    }
+
+    for (position, i) in positions.values() {
+        let value = brain.values[*i].clamp(-1.0, 1.0);
+        let fill = if value >= 0.0 { Color::new(0.2, 0.2 + 0.8 * value, 0.2, 1.0) } else { Color::new(0.2 - 0.8 * value, 0.2, 0.2, 1.0) };
+        draw_circle(position.x, position.y, NODE_RADIUS, fill);
+
+        let outline = match brain.nodes[*i].kind {
+            NodeKind::Input(..) => LIGHTGRAY,
+            NodeKind::Hidden => PINK,
+            NodeKind::Output(_) => WHITE,
+        };
+        draw_circle_lines(position.x, position.y, NODE_RADIUS, 1.0, outline);
+    }
+}
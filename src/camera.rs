@@ -0,0 +1,145 @@
+use macroquad::prelude::*;
+use crate::layout::Layout;
+use crate::utils::{WIDTH, HEIGHT, CELL_SIZE, MAX_ZOOM};
+
+/// View onto the world area of the window. `x`/`y` is the tile shown at the viewport's top left
+/// corner and `zoom` is the on-screen size of one tile in pixels.
+pub struct WorldCamera {
+    pub x: f32,
+    pub y: f32,
+    pub zoom: f32,
+    pub follow: bool, // keep the selected organism centred
+    pub fitted: bool, // keep the whole world in view as the window resizes, until the user zooms
+    drag_start: Option<(Vec2, f32, f32)>, // mouse position and camera position when the drag began
+}
+
+impl Default for WorldCamera {
+    fn default() -> Self {
+        WorldCamera::new()
+    }
+}
+
+impl WorldCamera {
+    pub fn new() -> Self {
+        WorldCamera {
+            x: 0.0,
+            y: 0.0,
+            zoom: CELL_SIZE,
+            follow: false,
+            fitted: true,
+            drag_start: None,
+        }
+    }
+
+    /// Screen size of the world area, which the camera fills whatever the zoom.
+    pub fn viewport() -> (f32, f32) {
+        let world = Layout::current().world;
+        (world.w, world.h)
+    }
+
+    /// Zoom at which the whole world just fits the viewport. It is also the furthest the view zooms out.
+    pub fn fit_zoom() -> f32 {
+        let (width, height) = WorldCamera::viewport();
+        (width / WIDTH as f32).min(height / HEIGHT as f32).max(1.0)
+    }
+
+    /// Refits a fitted view to the current window, and keeps any other view inside the zoom range.
+    pub fn fit_to_window(&mut self) {
+        let fit = WorldCamera::fit_zoom();
+        self.zoom = if self.fitted { fit } else { self.zoom.clamp(fit, MAX_ZOOM.max(fit)) };
+        self.clamp();
+    }
+
+    pub fn contains_screen(screen_x: f32, screen_y: f32) -> bool {
+        let (width, height) = WorldCamera::viewport();
+        screen_x >= 0.0 && screen_y >= 0.0 && screen_x < width && screen_y < height
+    }
+
+    pub fn world_to_screen(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x - self.x) * self.zoom, (y - self.y) * self.zoom)
+    }
+
+    pub fn screen_to_world(&self, screen_x: f32, screen_y: f32) -> (f32, f32) {
+        (self.x + screen_x / self.zoom, self.y + screen_y / self.zoom)
+    }
+
+    /// Tile under a screen position, or None outside the world view or the world itself.
+    pub fn tile_at_screen(&self, screen_x: f32, screen_y: f32) -> Option<(usize, usize)> {
+        if !WorldCamera::contains_screen(screen_x, screen_y) {
+            return None;
+        }
+        let (x, y) = self.screen_to_world(screen_x, screen_y);
+        if x < 0.0 || y < 0.0 || x >= WIDTH as f32 || y >= HEIGHT as f32 {
+            return None;
+        }
+        Some((x as usize, y as usize))
+    }
+
+    /// Half-open ranges of the tiles at least partly inside the viewport, so drawing can skip the rest.
+    pub fn visible_tiles(&self) -> ((usize, usize), (usize, usize)) {
+        let (width, height) = WorldCamera::viewport();
+        let (left, top) = self.screen_to_world(0.0, 0.0);
+        let (right, bottom) = self.screen_to_world(width, height);
+        (
+            (left.floor().max(0.0) as usize, (right.ceil().max(0.0) as usize).min(WIDTH)),
+            (top.floor().max(0.0) as usize, (bottom.ceil().max(0.0) as usize).min(HEIGHT)),
+        )
+    }
+
+    /// Centres the view on a world position.
+    pub fn center_on(&mut self, x: f32, y: f32) {
+        let (width, height) = WorldCamera::viewport();
+        self.x = x - width / self.zoom / 2.0;
+        self.y = y - height / self.zoom / 2.0;
+        self.clamp();
+    }
+
+    /// Mouse wheel zooms around the cursor, dragging with the right or middle button pans (and stops
+    /// following), F toggles following the selection and Home resets the view.
+    pub fn handle_input(&mut self) {
+        let (mouse_x, mouse_y) = mouse_position();
+        let over_world = WorldCamera::contains_screen(mouse_x, mouse_y);
+
+        let wheel = mouse_wheel().1;
+        if over_world && wheel != 0.0 {
+            let (world_x, world_y) = self.screen_to_world(mouse_x, mouse_y);
+            let factor = if wheel > 0.0 { 1.1 } else { 1.0 / 1.1 };
+            let fit = WorldCamera::fit_zoom();
+            self.zoom = (self.zoom * factor).clamp(fit, MAX_ZOOM.max(fit));
+            self.fitted = self.zoom <= fit;
+            self.x = world_x - mouse_x / self.zoom;
+            self.y = world_y - mouse_y / self.zoom;
+            self.clamp();
+        }
+
+        let pan_pressed = is_mouse_button_pressed(MouseButton::Right) || is_mouse_button_pressed(MouseButton::Middle);
+        let pan_down = is_mouse_button_down(MouseButton::Right) || is_mouse_button_down(MouseButton::Middle);
+        if pan_pressed && over_world {
+            self.drag_start = Some((vec2(mouse_x, mouse_y), self.x, self.y));
+            self.follow = false;
+        }
+        if !pan_down {
+            self.drag_start = None;
+        }
+        if let Some((start, camera_x, camera_y)) = self.drag_start {
+            self.x = camera_x - (mouse_x - start.x) / self.zoom;
+            self.y = camera_y - (mouse_y - start.y) / self.zoom;
+            self.clamp();
+        }
+
+        if is_key_pressed(KeyCode::F) {
+            self.follow = !self.follow;
+        }
+        if is_key_pressed(KeyCode::Home) {
+            *self = WorldCamera::new();
+        }
+    }
+
+    /// Keeps the view inside the world, centring it on any axis where the world is smaller than the view.
+    fn clamp(&mut self) {
+        let (width, height) = WorldCamera::viewport();
+        let (view_width, view_height) = (width / self.zoom, height / self.zoom);
+        self.x = if view_width >= WIDTH as f32 { (WIDTH as f32 - view_width) / 2.0 } else { self.x.clamp(0.0, WIDTH as f32 - view_width) };
+        self.y = if view_height >= HEIGHT as f32 { (HEIGHT as f32 - view_height) / 2.0 } else { self.y.clamp(0.0, HEIGHT as f32 - view_height) };
+    }
+}
@@ -0,0 +1,198 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use macroquad::prelude::*;
+use crate::camera::WorldCamera;
+use crate::organism_manager::OrganismManager;
+use crate::raster::{self, Canvas};
+use crate::utils::{CAPTURE_DIR, CAPTURE_SCALE, CAPTURE_INTERVAL, CAPTURE_MENU_TILES, GIF_FRAME_DELAY};
+
+/// How recorded frames are rendered and where they are written.
+#[derive(Debug, Clone)]
+pub struct CaptureOptions {
+    pub directory: PathBuf,
+    pub scale: usize, // pixels per tile
+    pub interval: u128, // ticks between frames
+    pub include_menu: bool, // put the stats panel to the right of the world
+    pub gif: bool, // also assemble the frames into `timelapse.gif`
+}
+
+impl Default for CaptureOptions {
+    fn default() -> Self {
+        CaptureOptions {
+            directory: PathBuf::from(CAPTURE_DIR),
+            scale: CAPTURE_SCALE,
+            interval: CAPTURE_INTERVAL,
+            include_menu: true,
+            gif: true,
+        }
+    }
+}
+
+/// A recording in progress. Each frame is written as a PNG straight away and, if asked for, appended to the
+/// time-lapse GIF, so nothing but the encoder is kept in memory.
+pub struct Recorder {
+    pub options: CaptureOptions,
+    frames: usize,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl Recorder {
+    pub fn start(options: CaptureOptions) -> io::Result<Self> {
+        fs::create_dir_all(&options.directory)?;
+        Ok(Recorder { options, frames: 0, gif: None })
+    }
+
+    pub fn is_due(&self, tick: u128) -> bool {
+        tick.is_multiple_of(self.options.interval.max(1))
+    }
+
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Writes `frame_<tick>.png` and adds the image to the time-lapse.
+    pub fn record(&mut self, canvas: &Canvas, tick: u128) -> io::Result<()> {
+        canvas.save_png(&self.options.directory.join(format!("frame_{:08}.png", tick)))?;
+        if self.options.gif {
+            let (Ok(width), Ok(height)) = (u16::try_from(canvas.width), u16::try_from(canvas.height)) else {
+                return Err(io::Error::other("frames are too large for a GIF"));
+            };
+            if self.gif.is_none() {
+                let file = BufWriter::new(File::create(self.options.directory.join("timelapse.gif"))?);
+                let mut encoder = gif::Encoder::new(file, width, height, &[]).map_err(io::Error::other)?;
+                encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+                self.gif = Some(encoder);
+            }
+            let mut pixels = canvas.pixels().to_vec();
+            let mut frame = gif::Frame::from_rgba_speed(width, height, &mut pixels, 10);
+            frame.delay = GIF_FRAME_DELAY;
+            if let Some(encoder) = self.gif.as_mut() {
+                encoder.write_frame(&frame).map_err(io::Error::other)?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Closes the time-lapse and returns how many frames were recorded.
+    pub fn finish(self) -> io::Result<usize> {
+        if let Some(encoder) = self.gif {
+            encoder.into_inner()?;
+        }
+        Ok(self.frames)
+    }
+}
+
+/// Where the P and R keys write, the recording if one is running, and the last capture result to show on screen.
+pub struct Capture {
+    pub directory: PathBuf,
+    pub recorder: Option<Recorder>,
+    message: String,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Capture::new()
+    }
+}
+
+impl Capture {
+    pub fn new() -> Self {
+        Capture { directory: PathBuf::from(CAPTURE_DIR), recorder: None, message: String::new() }
+    }
+
+    /// The last capture result, such as why a recording stopped.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl OrganismManager {
+    /// Rasterises the world coloured by the current render mode, with the stats panel to its right if asked.
+    /// Needs no window, so it also works when running headless.
+    pub fn capture_image(&self, scale: usize, include_menu: bool) -> Canvas {
+        let world = raster::rasterise_world(&self.grid, &self.organism_colors(), scale);
+        if !include_menu {
+            return world;
+        }
+        let menu = raster::rasterise_menu(&self.current_stats(), self.history(), CAPTURE_MENU_TILES * scale, scale);
+        let mut canvas = Canvas::new(world.width + menu.width, world.height, BLACK);
+        canvas.blit(&world, 0, 0);
+        canvas.blit(&menu, world.width, 0);
+        canvas
+    }
+
+    pub fn save_snapshot(&self, path: &Path, scale: usize, include_menu: bool) -> io::Result<()> {
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        self.capture_image(scale, include_menu).save_png(path)
+    }
+
+    /// Starts recording a frame every `options.interval` ticks, replacing any recording already running.
+    pub fn start_recording(&mut self, options: CaptureOptions) -> io::Result<()> {
+        self.stop_recording()?;
+        self.capture.recorder = Some(Recorder::start(options)?);
+        Ok(())
+    }
+
+    /// Finishes the running recording, if any, and returns how many frames it has.
+    pub fn stop_recording(&mut self) -> io::Result<usize> {
+        match self.capture.recorder.take() {
+            Some(recorder) => recorder.finish(),
+            None => Ok(0),
+        }
+    }
+
+    /// Called after every tick. A recording that fails to write is stopped and the error becomes the capture message.
+    pub fn record_frame(&mut self) {
+        let (scale, include_menu) = match &self.capture.recorder {
+            Some(recorder) if recorder.is_due(self.frame) => (recorder.options.scale, recorder.options.include_menu),
+            _ => return,
+        };
+        let canvas = self.capture_image(scale, include_menu);
+        let Some(recorder) = self.capture.recorder.as_mut() else {
+            return;
+        };
+        if let Err(error) = recorder.record(&canvas, self.frame) {
+            self.capture.message = format!("Recording stopped: {}", error);
+            self.capture.recorder = None;
+        }
+    }
+
+    /// P saves a snapshot of the world and menu and R starts or stops a time-lapse recording, both in the capture directory.
+    /// The outcome is shown in the top right corner of the world view.
+    pub fn update_capture(&mut self) {
+        if is_key_pressed(KeyCode::P) {
+            let path = self.capture.directory.join(format!("snapshot_{:08}.png", self.frame));
+            self.capture.message = match self.save_snapshot(&path, CAPTURE_SCALE, true) {
+                Ok(()) => format!("Saved {}", path.display()),
+                Err(error) => format!("Snapshot failed: {}", error),
+            };
+        }
+        if is_key_pressed(KeyCode::R) {
+            self.capture.message = if self.capture.recorder.is_some() {
+                match self.stop_recording() {
+                    Ok(frames) => format!("Recorded {} frames to {}", frames, self.capture.directory.display()),
+                    Err(error) => format!("Recording failed: {}", error),
+                }
+            } else {
+                let options = CaptureOptions { directory: self.capture.directory.clone(), ..CaptureOptions::default() };
+                match self.start_recording(options) {
+                    Ok(()) => String::new(),
+                    Err(error) => format!("Recording failed: {}", error),
+                }
+            };
+        }
+
+        let status = match &self.capture.recorder {
+            Some(recorder) => format!("REC {} frames (R: stop)", recorder.frames()),
+            None => self.capture.message.clone(),
+        };
+        if !status.is_empty() {
+            let width = measure_text(status.as_str(), None, 20, 1.0).width;
+            draw_text(status.as_str(), WorldCamera::viewport().0 - width - 10.0, 54.0, 20.0, RED);
+        }
+    }
+}
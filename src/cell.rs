@@ -38,6 +38,20 @@ pub enum Cell {
 }
 
 impl Cell {
+    /// Every cell an organism can be built from, with one entry per eye orientation.
+    pub const PALETTE: [Cell; 10] = [
+        Cell::Mouth,
+        Cell::Producer,
+        Cell::Mover,
+        Cell::Killer,
+        Cell::Armor,
+        Cell::Brain,
+        Cell::Eye(EyeType::Up),
+        Cell::Eye(EyeType::Right),
+        Cell::Eye(EyeType::Down),
+        Cell::Eye(EyeType::Left),
+    ];
+
     pub fn from_string(s: &str) -> Option<Cell> {
         match s {
             "Mouth" => Some(Cell::Mouth),
@@ -87,4 +101,25 @@ impl EyeType {
             EyeType::Right => Direction::Right,
         }
     }
+    pub fn from_direction(dir: Direction) -> EyeType {
+        match dir {
+            Direction::Down => EyeType::Down,
+            Direction::Left => EyeType::Left,
+            Direction::Right => EyeType::Right,
+            Direction::Up | Direction::None => EyeType::Up,
+        }
+    }
+    pub fn rotated(self, clockwise: bool) -> EyeType {
+        EyeType::from_direction(self.to_direction().rotated(clockwise))
+    }
+}
+
+impl Cell {
+    /// Rotates orientation dependent cells, every other cell is returned unchanged.
+    pub fn rotated(self, clockwise: bool) -> Cell {
+        match self {
+            Cell::Eye(eye_type) => Cell::Eye(eye_type.rotated(clockwise)),
+            cell => cell,
+        }
+    }
 }
\ No newline at end of file
@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use macroquad::prelude::*;
+use crate::organism_manager::SimData;
+use crate::utils::{HISTORY_LEN, CHART_SPECIES};
+
+const LABEL_HEIGHT: f32 = 14.0;
+
+/// Stable colour for a species, derived from its anatomy hash.
+pub fn species_color(species: u64) -> Color {
+    let channel = |shift: u32| 0.3 + 0.7 * ((species >> shift) & 0xff) as f32 / 255.0;
+    Color::new(channel(0), channel(8), channel(16), 1.0)
+}
+
+/// Ring buffer of recent `SimData` samples that the charts are drawn from.
+pub struct SimHistory {
+    samples: VecDeque<SimData>,
+}
+
+impl Default for SimHistory {
+    fn default() -> Self {
+        SimHistory::new()
+    }
+}
+
+impl SimHistory {
+    pub fn new() -> Self {
+        SimHistory { samples: VecDeque::with_capacity(HISTORY_LEN) }
+    }
+
+    pub fn push(&mut self, sample: SimData) {
+        if self.samples.len() == HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> &VecDeque<SimData> {
+        &self.samples
+    }
+
+    /// Deaths of each kind between consecutive samples, since the counters in `SimData` are totals.
+    fn deaths(&self, count: fn(&SimData) -> usize) -> Vec<f32> {
+        self.samples
+            .iter()
+            .zip(self.samples.iter().skip(1))
+            .map(|(previous, sample)| count(sample).saturating_sub(count(previous)) as f32)
+            .collect()
+    }
+
+    /// Draws the population, death, food, size and species charts stacked inside the given rectangle.
+    pub fn draw(&self, x: f32, y: f32, width: f32, height: f32) {
+        draw_rectangle(x, y, width, height, Color::new(0.12, 0.12, 0.14, 1.0));
+        if self.samples.len() < 2 {
+            draw_text("Collecting history...", x + 10.0, y + 20.0, 18.0, WHITE);
+            return;
+        }
+
+        let chart_height = height / 5.0;
+        let series = |value: fn(&SimData) -> f32| self.samples.iter().map(value).collect::<Vec<f32>>();
+
+        let population = series(|sample| sample.organism_num as f32);
+        draw_line_chart(x, y, width, chart_height, "Population", &[(population, SKYBLUE)]);
+
+        let deaths = [
+            (self.deaths(|sample| sample.hunger_death), ORANGE),
+            (self.deaths(|sample| sample.age_death), LIGHTGRAY),
+            (self.deaths(|sample| sample.predation_death), RED),
+        ];
+        draw_line_chart(x, y + chart_height, width, chart_height, "Deaths (hunger/age/predation)", &deaths);
+
+        let food = series(|sample| sample.food_amount as f32);
+        draw_line_chart(x, y + 2.0 * chart_height, width, chart_height, "Food", &[(food, BLUE)]);
+
+        let size = series(|sample| sample.mean_size);
+        draw_line_chart(x, y + 3.0 * chart_height, width, chart_height, "Mean size", &[(size, GREEN)]);
+
+        self.draw_species_chart(x, y + 4.0 * chart_height, width, chart_height);
+    }
+
+    /// One column per sample, split into the most populous species and everything else.
+    fn draw_species_chart(&self, x: f32, y: f32, width: f32, height: f32) {
+        draw_text(format!("Species (top {})", CHART_SPECIES).as_str(), x + 4.0, y + LABEL_HEIGHT - 2.0, 16.0, WHITE);
+        let plot_y = y + LABEL_HEIGHT;
+        let plot_height = height - LABEL_HEIGHT - 2.0;
+        let column_width = width / (HISTORY_LEN - 1) as f32;
+        let max = self.samples.iter().map(|sample| sample.organism_num).max().unwrap_or(0).max(1) as f32;
+
+        for (i, sample) in self.samples.iter().enumerate() {
+            let column_x = x + i as f32 * column_width;
+            let mut top = plot_y + plot_height;
+            let mut counted = 0;
+            for (species, count) in sample.species_counts.iter() {
+                let bar = *count as f32 / max * plot_height;
+                top -= bar;
+                draw_rectangle(column_x, top, column_width.max(1.0), bar, species_color(*species));
+                counted += count;
+            }
+            let other = sample.organism_num.saturating_sub(counted) as f32 / max * plot_height;
+            draw_rectangle(column_x, top - other, column_width.max(1.0), other, DARKGRAY);
+        }
+    }
+}
+
+/// Draws each series as a polyline scaled to the largest value in the chart, oldest sample on the left.
+fn draw_line_chart(x: f32, y: f32, width: f32, height: f32, label: &str, series: &[(Vec<f32>, Color)]) {
+    let latest: Vec<String> = series
+        .iter()
+        .map(|(values, _)| values.last().copied().unwrap_or(0.0))
+        .map(|value| if value.fract() == 0.0 { format!("{:.0}", value) } else { format!("{:.1}", value) })
+        .collect();
+    draw_text(format!("{} {}", label, latest.join("/")).as_str(), x + 4.0, y + LABEL_HEIGHT - 2.0, 16.0, WHITE);
+
+    let plot_y = y + LABEL_HEIGHT;
+    let plot_height = height - LABEL_HEIGHT - 2.0;
+    let max = series.iter().flat_map(|(values, _)| values.iter()).fold(0.0f32, |max, value| max.max(*value)).max(1.0);
+    let step = width / (HISTORY_LEN - 1) as f32;
+    draw_line(x, plot_y + plot_height, x + width, plot_y + plot_height, 1.0, GRAY);
+
+    for (values, color) in series {
+        for (i, pair) in values.windows(2).enumerate() {
+            let (x1, x2) = (x + i as f32 * step, x + (i + 1) as f32 * step);
+            let y1 = plot_y + plot_height * (1.0 - pair[0] / max);
+            let y2 = plot_y + plot_height * (1.0 - pair[1] / max);
+            draw_line(x1, y1, x2, y2, 1.5, *color);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Instant;
+use crate::capture::CaptureOptions;
+use crate::gallery::SpeciesRegistry;
+use crate::library::GenomeLibrary;
+use crate::organism_manager::OrganismManager;
+use crate::mutation::MutationPolicy;
+use crate::params::{self, SimParams};
+use crate::tuner;
+use crate::utils::{SEED, GENOME_LIBRARY_FILE, TUNED_CONFIG_FILE};
+
+/// Names the file an error came from, since io errors on their own do not.
+fn in_file(path: &Path) -> impl FnOnce(io::Error) -> io::Error + '_ {
+    move |error| io::Error::new(error.kind(), format!("{}: {}", path.display(), error))
+}
+
+/// Applies the settings of `config`, then the `key = value` overrides given with --set.
+fn apply_config(config: Option<&Path>, settings: &[String], params: &mut SimParams, policy: &mut MutationPolicy) -> io::Result<()> {
+    if let Some(config) = config {
+        params::load_config(config, params, policy).map_err(in_file(config))?;
+    }
+    params::parse_config(&settings.join("\n"), params, policy);
+    Ok(())
+}
+
+/// A world seeded from `seed`, or `SEED` if none is given, with the settings of `config` and `settings` applied
+/// before the first organisms are placed.
+pub fn new_world(config: Option<&Path>, settings: &[String], seed: Option<u64>) -> io::Result<OrganismManager> {
+    let mut organism_manager = OrganismManager::with_seed(seed.unwrap_or(SEED));
+    apply_config(config, settings, &mut organism_manager.params, &mut organism_manager.mutation_policy)?;
+    organism_manager.init();
+    Ok(organism_manager)
+}
+
+/// Reads the world of a snapshot. A `config` or `settings` given on the command line take the place of the
+/// snapshot's own settings and a `seed` restarts its random streams from that seed.
+pub fn load_snapshot(path: &Path, config: Option<&Path>, settings: &[String], seed: Option<u64>) -> io::Result<OrganismManager> {
+    let mut organism_manager = OrganismManager::load_world(path).map_err(in_file(path))?;
+    apply_config(config, settings, &mut organism_manager.params, &mut organism_manager.mutation_policy)?;
+    if let Some(seed) = seed {
+        organism_manager.reseed(seed);
+    }
+    Ok(organism_manager)
+}
+
+/// Runs `ticks` ticks without any display, printing the counters ten times along the way. Frames are recorded
+/// if `record` is given and the run is saved as a snapshot to `output`.
+pub fn headless(config: Option<&Path>, settings: &[String], seed: Option<u64>, ticks: u128, record: Option<CaptureOptions>, output: Option<&Path>) -> io::Result<()> {
+    let mut organism_manager = new_world(config, settings, seed)?;
+    if let Some(options) = record {
+        organism_manager.start_recording(options)?;
+    }
+
+    let report_interval = (ticks / 10).max(1);
+    let start = Instant::now();
+    while organism_manager.frame < ticks {
+        let recording = organism_manager.capture.recorder.is_some();
+        organism_manager.tick();
+        if recording && organism_manager.capture.recorder.is_none() {
+            eprintln!("{}", organism_manager.capture.message());
+        }
+        if organism_manager.frame.is_multiple_of(report_interval) || organism_manager.frame == ticks {
+            let stats = organism_manager.current_stats();
+            println!("tick {:>8}  organisms {:>5}  food {:>5}  deaths {}/{}/{}  extinctions {}  {:.1}s",
+                stats.frame, stats.organism_num, stats.food_amount, stats.hunger_death, stats.age_death, stats.predation_death,
+                stats.extinctions, start.elapsed().as_secs_f32());
+        }
+    }
+
+    if organism_manager.capture.recorder.is_some() {
+        println!("Recorded {} frames", organism_manager.stop_recording()?);
+    }
+    if let Some(output) = output {
+        organism_manager.save_world(output).map_err(in_file(output))?;
+        println!("Saved snapshot to {}", output.display());
+    }
+    Ok(())
+}
+
+/// Searches for settings that keep the population going and writes the best ones as a config file.
+pub fn tune(config: Option<&Path>, settings: &[String], seed: Option<u64>, output: Option<&Path>) -> io::Result<()> {
+    let (mut base, mut policy) = (SimParams::default(), MutationPolicy::default());
+    apply_config(config, settings, &mut base, &mut policy)?;
+    let best = tuner::tune(&base, &policy, seed.unwrap_or(SEED));
+    let output = output.unwrap_or(Path::new(TUNED_CONFIG_FILE));
+    params::save_config(output, &best, &policy).map_err(in_file(output))?;
+    println!("Saved the best settings to {}", output.display());
+    Ok(())
+}
+
+/// Prints the statistics of a snapshot's world, or writes them to `output`.
+pub fn stats(world: &OrganismManager, output: Option<&Path>) -> io::Result<()> {
+    let report = format!("Seed: {}\n{}", world.seed, world.stats_report());
+    match output {
+        Some(output) => fs::write(output, report),
+        None => {
+            print!("{}", report);
+            Ok(())
+        }
+    }
+}
+
+/// Adds the anatomies of the `top` most populous species in a snapshot's world to the genome library at
+/// `output`, or `GENOME_LIBRARY_FILE`, where the designer and editor can load them.
+pub fn export_genome(world: &OrganismManager, top: usize, output: Option<&Path>) -> io::Result<()> {
+    let output = output.unwrap_or(Path::new(GENOME_LIBRARY_FILE));
+    let mut library = GenomeLibrary::new();
+    match library.load(output) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+
+    let ranking = SpeciesRegistry::living_counts(&world.organisms);
+    if ranking.is_empty() {
+        return Err(io::Error::other("no organisms are alive at the end of the snapshot"));
+    }
+    for (species, living) in ranking.iter().take(top) {
+        let Some(record) = world.species().get(*species) else {
+            continue;
+        };
+        let name = format!("species-{:08x}", *species as u32);
+        library.add(name.clone(), record.anatomy.clone());
+        println!("Exported {} ({} living, {} cells)", name, living, record.anatomy.len());
+    }
+    library.save(output)?;
+    println!("Saved the genome library to {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use std::time::Instant;
+use macroquad::prelude::*;
+use crate::ui;
+use crate::layout::{Layout, BUTTON_HEIGHT};
+use crate::utils::{MAX_TICKS_PER_FRAME, FAST_FORWARD_BUDGET};
+
+/// Pause, single-step and fast-forward state for the main loop of the window or terminal frontend, plus a
+/// ticks-per-second readout.
+pub struct SimControls {
+    pub paused: bool,
+    pub speed: usize, // ticks run per rendered frame
+    step_requested: bool,
+    ticks_per_second: f32,
+    counted_ticks: usize,
+    count_start: Instant,
+}
+
+impl Default for SimControls {
+    fn default() -> Self {
+        SimControls::new()
+    }
+}
+
+impl SimControls {
+    pub fn new() -> Self {
+        SimControls {
+            paused: false,
+            speed: 1,
+            step_requested: false,
+            ticks_per_second: 0.0,
+            counted_ticks: 0,
+            count_start: Instant::now(),
+        }
+    }
+
+    /// Space pauses or resumes, N (or the right arrow) steps one tick while paused and +/- double or halve the speed.
+    pub fn handle_keys(&mut self) {
+        if is_key_pressed(KeyCode::Space) {
+            self.paused = !self.paused;
+        }
+        if is_key_pressed(KeyCode::N) || is_key_pressed(KeyCode::Right) {
+            self.step();
+        }
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            self.faster();
+        }
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            self.slower();
+        }
+    }
+
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.step_requested = true;
+    }
+
+    pub fn ticks_per_second(&self) -> f32 {
+        self.ticks_per_second
+    }
+
+    pub fn faster(&mut self) {
+        self.speed = (self.speed * 2).min(MAX_TICKS_PER_FRAME);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = (self.speed / 2).max(1);
+    }
+
+    /// Runs this frame's ticks: `speed` of them, or with graphics off as many as fit, but never past the
+    /// frame's time budget once the first tick has run, so a large world cannot stall the window.
+    pub fn run_frame(&mut self, graphics_on: bool, mut tick: impl FnMut()) {
+        let start = Instant::now();
+        let mut ticks = 0;
+        if self.paused {
+            if self.step_requested {
+                tick();
+                ticks = 1;
+            }
+        } else {
+            loop {
+                tick();
+                ticks += 1;
+                if (graphics_on && ticks >= self.speed) || start.elapsed().as_secs_f64() >= FAST_FORWARD_BUDGET {
+                    break;
+                }
+            }
+        }
+        self.step_requested = false;
+        self.count_ticks(ticks);
+    }
+
+    fn count_ticks(&mut self, ticks: usize) {
+        self.counted_ticks += ticks;
+        let elapsed = self.count_start.elapsed().as_secs_f64();
+        if elapsed >= 1.0 {
+            self.ticks_per_second = (self.counted_ticks as f64 / elapsed) as f32;
+            self.counted_ticks = 0;
+            self.count_start = Instant::now();
+        }
+    }
+
+    /// Draws the pause, step and speed buttons under the sim menu and handles clicks on them.
+    pub fn draw(&mut self, layout: &Layout) {
+        let x = layout.controls.x;
+        let y = layout.controls.y + BUTTON_HEIGHT + 5.0;
+        let height = BUTTON_HEIGHT;
+        let buttons = [
+            (if self.paused { "Play" } else { "Pause" }, 60.0),
+            ("Step", 50.0),
+            ("-", 30.0),
+            ("+", 30.0),
+        ];
+
+        let mut button_x = x;
+        let mut clicked = None;
+        for (i, (label, width)) in buttons.iter().enumerate() {
+            if ui::button(button_x, y, *width, height, label, false) {
+                clicked = Some(i);
+            }
+            button_x += width + 5.0;
+        }
+        match clicked {
+            Some(0) => self.paused = !self.paused,
+            Some(1) => self.step(),
+            Some(2) => self.slower(),
+            Some(3) => self.faster(),
+            _ => {}
+        }
+
+        let status = if self.paused { "paused".to_owned() } else { format!("{}x", self.speed) };
+        draw_text(format!("Speed: {}  TPS: {:.0}", status, self.ticks_per_second).as_str(), x, y + height + 20.0, 20.0, BLACK);
+    }
+}
@@ -0,0 +1,192 @@
+use macroquad::prelude::*;
+use crate::camera::WorldCamera;
+use crate::cell::{Cell, EyeType};
+use crate::editor::EditorTool;
+use crate::grid::Grid;
+use crate::organism::Organism;
+use crate::organism_manager::OrganismManager;
+use crate::ui;
+use crate::utils::GENOME_LIBRARY_FILE;
+
+const DESIGN_RADIUS: i32 = 6; // the design grid spans -DESIGN_RADIUS..=DESIGN_RADIUS on both axes
+const DESIGN_TILE: f32 = 28.0;
+const PANEL_WIDTH: f32 = 640.0;
+const PANEL_HEIGHT: f32 = 560.0;
+
+/// Anatomy being built in the organism designer, with the anchor at (0, 0).
+pub struct Designer {
+    pub open: bool,
+    pub cells: Vec<(i32, i32, Cell)>,
+    pub palette_index: usize, // index into Cell::PALETTE
+    message: String,
+}
+
+impl Default for Designer {
+    fn default() -> Self {
+        Designer::new()
+    }
+}
+
+impl Designer {
+    pub fn new() -> Self {
+        Designer {
+            open: false,
+            cells: Vec::new(),
+            palette_index: 0,
+            message: String::new(),
+        }
+    }
+
+    /// Top left corner of the panel, centred on the world view.
+    pub fn panel_origin() -> (f32, f32) {
+        let (width, height) = WorldCamera::viewport();
+        ((width - PANEL_WIDTH) / 2.0, (height - PANEL_HEIGHT) / 2.0)
+    }
+
+    pub fn contains_screen(&self, screen_x: f32, screen_y: f32) -> bool {
+        let (x, y) = Designer::panel_origin();
+        self.open && ui::contains(x, y, PANEL_WIDTH, PANEL_HEIGHT, screen_x, screen_y)
+    }
+
+    /// Left click places the palette cell (clicking the same cell again removes it), right click turns an eye clockwise.
+    fn edit_tile(&mut self, dx: i32, dy: i32, rotate: bool) {
+        let index = self.cells.iter().position(|(x, y, _)| *x == dx && *y == dy);
+        let cell = Cell::PALETTE[self.palette_index];
+        match (index, rotate) {
+            (Some(i), true) => self.cells[i].2 = self.cells[i].2.rotated(true),
+            (None, true) => {}
+            (Some(i), false) if self.cells[i].2 == cell => {
+                self.cells.remove(i);
+            }
+            (Some(i), false) => self.cells[i].2 = cell,
+            (None, false) => self.cells.push((dx, dy, cell)),
+        }
+    }
+
+    /// Problems that stop the design from being saved, empty if it is valid.
+    pub fn errors(&self) -> Vec<&'static str> {
+        let mut errors = Vec::new();
+        if self.cells.is_empty() {
+            errors.push("Place at least one cell");
+        } else if !Organism::cells_connected(&self.cells) {
+            errors.push("Cells must all be connected");
+        }
+        errors
+    }
+
+    /// Observations about how the design will live, which do not block saving.
+    pub fn notes(&self, lifetime_multiplier: i32) -> Vec<String> {
+        let has = |wanted: fn(&Cell) -> bool| self.cells.iter().any(|(_, _, cell)| wanted(cell));
+        let mut notes = Vec::new();
+        if !has(|cell| matches!(cell, Cell::Mouth | Cell::Producer)) {
+            notes.push("No mouth or producer: it cannot gain energy".to_owned());
+        }
+        match Organism::brain_quality(&self.cells) {
+            Some((eyes, brains)) => notes.push(format!("Brain with {} eyes and {} brain cells", eyes, brains)),
+            None if has(|cell| matches!(cell, Cell::Eye(_) | Cell::Brain)) => notes.push("No brain: needs an eye and a mover".to_owned()),
+            None => {}
+        }
+        notes.push(format!("{} cells, lifetime {}", self.cells.len(), self.cells.len() as i32 * lifetime_multiplier));
+        notes
+    }
+}
+
+impl OrganismManager {
+    /// Draws the designer panel and handles its input. D opens and closes it.
+    pub fn update_designer(&mut self) {
+        if is_key_pressed(KeyCode::D) {
+            self.designer.open = !self.designer.open;
+        }
+        if !self.designer.open || !self.grid.graphics_on() {
+            return;
+        }
+
+        let (x, y) = Designer::panel_origin();
+        draw_rectangle(x, y, PANEL_WIDTH, PANEL_HEIGHT, Color::new(0.1, 0.1, 0.12, 0.95));
+        draw_text("Organism Designer (D closes)", x + 20.0, y + 30.0, 24.0, WHITE);
+
+        let grid_x = x + 20.0;
+        let grid_y = y + 50.0;
+        let tiles = 2 * DESIGN_RADIUS + 1;
+        let (mouse_x, mouse_y) = mouse_position();
+        for row in 0..tiles {
+            for column in 0..tiles {
+                let (dx, dy) = (column - DESIGN_RADIUS, row - DESIGN_RADIUS);
+                let (tile_x, tile_y) = (grid_x + column as f32 * DESIGN_TILE, grid_y + row as f32 * DESIGN_TILE);
+                let cell = self.designer.cells.iter().find(|(x, y, _)| *x == dx && *y == dy).map(|(_, _, cell)| *cell);
+                draw_rectangle(tile_x, tile_y, DESIGN_TILE - 1.0, DESIGN_TILE - 1.0, cell.map_or(DARKGRAY, |cell| Grid::get_cell_color(&cell)));
+                if let Some(Cell::Eye(eye_type)) = cell {
+                    let direction = eye_type.to_direction();
+                    let center = (tile_x + DESIGN_TILE / 2.0, tile_y + DESIGN_TILE / 2.0);
+                    draw_line(center.0, center.1, center.0 + direction.x_offset() * DESIGN_TILE / 2.0, center.1 + direction.y_offset() * DESIGN_TILE / 2.0, 4.0, BLACK);
+                }
+                if dx == 0 && dy == 0 {
+                    draw_rectangle_lines(tile_x, tile_y, DESIGN_TILE - 1.0, DESIGN_TILE - 1.0, 2.0, WHITE);
+                }
+
+                if ui::contains(tile_x, tile_y, DESIGN_TILE, DESIGN_TILE, mouse_x, mouse_y) {
+                    if is_mouse_button_pressed(MouseButton::Left) {
+                        self.designer.edit_tile(dx, dy, false);
+                    } else if is_mouse_button_pressed(MouseButton::Right) {
+                        self.designer.edit_tile(dx, dy, true);
+                    }
+                }
+            }
+        }
+
+        let palette_x = grid_x + tiles as f32 * DESIGN_TILE + 20.0;
+        for (i, cell) in Cell::PALETTE.iter().enumerate() {
+            let swatch_y = grid_y + i as f32 * 30.0;
+            let label = match cell {
+                Cell::Eye(eye_type) => format!("Eye {}", match eye_type { EyeType::Up => "up", EyeType::Right => "right", EyeType::Down => "down", EyeType::Left => "left" }),
+                cell => format!("{:?}", cell),
+            };
+            draw_rectangle(palette_x, swatch_y, 24.0, 24.0, Grid::get_cell_color(cell));
+            if ui::button(palette_x + 30.0, swatch_y, 180.0, 24.0, label.as_str(), i == self.designer.palette_index) {
+                self.designer.palette_index = i;
+            }
+        }
+
+        let errors = self.designer.errors();
+        let mut text_y = grid_y + tiles as f32 * DESIGN_TILE + 24.0;
+        for error in errors.iter() {
+            draw_text(error, grid_x, text_y, 18.0, RED);
+            text_y += 20.0;
+        }
+        for note in self.designer.notes(self.params.lifetime_multiplier) {
+            draw_text(note.as_str(), grid_x, text_y, 18.0, LIGHTGRAY);
+            text_y += 20.0;
+        }
+
+        let button_y = y + PANEL_HEIGHT - 45.0;
+        let valid = errors.is_empty();
+        if ui::button(palette_x, button_y - 70.0, 100.0, 30.0, "Save", false) && valid {
+            let name = format!("Design {}", self.library.len());
+            self.editor.genome = self.library.add(name, self.designer.cells.clone());
+            self.designer.message = match self.library.save(GENOME_LIBRARY_FILE) {
+                Ok(()) => format!("Saved to {}", GENOME_LIBRARY_FILE),
+                Err(error) => format!("Could not save: {}", error),
+            };
+        }
+        if ui::button(palette_x + 110.0, button_y - 70.0, 100.0, 30.0, "Drop in", false) && valid {
+            let name = format!("Design {}", self.library.len());
+            self.editor.genome = self.library.add(name, self.designer.cells.clone());
+            self.editor.active = true;
+            self.editor.tool = EditorTool::Place;
+            self.designer.open = false;
+        }
+        if ui::button(palette_x, button_y - 35.0, 100.0, 30.0, "Load", false) {
+            if let Some(genome) = self.library.get(self.editor.genome) {
+                self.designer.cells = genome.cells.clone();
+                self.designer.message = format!("Loaded {}", genome.name);
+            }
+        }
+        if ui::button(palette_x + 110.0, button_y - 35.0, 100.0, 30.0, "Clear", false) {
+            self.designer.cells.clear();
+        }
+        if ui::button(palette_x, button_y, 210.0, 30.0, "Close", false) {
+            self.designer.open = false;
+        }
+        draw_text(self.designer.message.as_str(), x + 20.0, y + PANEL_HEIGHT - 15.0, 20.0, WHITE);
+    }
+}
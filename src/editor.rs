@@ -0,0 +1,389 @@
+use macroquad::prelude::*;
+use crate::camera::WorldCamera;
+use crate::cell::Cell;
+use crate::grid::{Grid, SpawnError};
+use crate::organism::Organism;
+use crate::organism_manager::OrganismManager;
+use crate::ui;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EditorTool {
+    Food,
+    Wall,
+    Erase,
+    Place,
+    Sketch,
+    Delete,
+    Clone,
+    Region,
+}
+
+impl EditorTool {
+    pub const ALL: [EditorTool; 8] = [
+        EditorTool::Food,
+        EditorTool::Wall,
+        EditorTool::Erase,
+        EditorTool::Place,
+        EditorTool::Sketch,
+        EditorTool::Delete,
+        EditorTool::Clone,
+        EditorTool::Region,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            EditorTool::Food => "Food",
+            EditorTool::Wall => "Wall",
+            EditorTool::Erase => "Erase",
+            EditorTool::Place => "Place",
+            EditorTool::Sketch => "Sketch",
+            EditorTool::Delete => "Delete",
+            EditorTool::Clone => "Clone",
+            EditorTool::Region => "Region",
+        }
+    }
+}
+
+/// What can be done to every tile or organism inside a selected region.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RegionAction {
+    FillFood,
+    FillWalls,
+    Clear,
+    Kill,
+}
+
+/// State of the world editor. While it is active, left clicks on the world apply the current tool instead of selecting.
+pub struct Editor {
+    pub active: bool,
+    pub tool: EditorTool,
+    pub genome: usize, // library entry placed by the Place tool
+    pub sketch: Vec<(usize, usize, Cell)>, // hand-drawn anatomy in world tiles
+    pub sketch_cell: usize, // index into Cell::PALETTE
+    pub region: Option<((usize, usize), (usize, usize))>, // inclusive min and max corners
+    drag_origin: Option<(usize, usize)>,
+    last_painted: Option<(usize, usize)>,
+    message: String,
+}
+
+const TOOLBAR_HEIGHT: f32 = 30.0;
+const TOOL_WIDTH: f32 = 70.0;
+const ACTION_WIDTH: f32 = 90.0;
+const GAP: f32 = 5.0;
+
+impl Default for Editor {
+    fn default() -> Self {
+        Editor::new()
+    }
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor {
+            active: false,
+            tool: EditorTool::Food,
+            genome: 0,
+            sketch: Vec::new(),
+            sketch_cell: 0,
+            region: None,
+            drag_origin: None,
+            last_painted: None,
+            message: String::new(),
+        }
+    }
+
+    fn toolbar_origin() -> (f32, f32) {
+        let (_, height) = WorldCamera::viewport();
+        (10.0, height - TOOLBAR_HEIGHT - 10.0)
+    }
+
+    fn toolbar_width(&self) -> f32 {
+        let tools = (EditorTool::ALL.len() + 2) as f32 * (TOOL_WIDTH + GAP);
+        let actions = if self.tool == EditorTool::Region && self.region.is_some() { 4.0 * (ACTION_WIDTH + GAP) } else { 0.0 };
+        tools + actions
+    }
+
+    pub fn over_toolbar(&self, screen_x: f32, screen_y: f32) -> bool {
+        let (x, y) = Editor::toolbar_origin();
+        let width = if self.active { self.toolbar_width() } else { TOOL_WIDTH + 20.0 };
+        ui::contains(x, y, width, TOOLBAR_HEIGHT, screen_x, screen_y)
+    }
+
+    /// Sketch cells relative to the anchor, the first cell drawn.
+    fn sketch_anatomy(&self, (anchor_x, anchor_y): (usize, usize)) -> Vec<(i32, i32, Cell)> {
+        self.sketch
+            .iter()
+            .map(|&(x, y, cell)| (x as i32 - anchor_x as i32, y as i32 - anchor_y as i32, cell))
+            .collect()
+    }
+
+    fn toggle_sketch_cell(&mut self, x: usize, y: usize) {
+        let cell = Cell::PALETTE[self.sketch_cell];
+        match self.sketch.iter().position(|&(sx, sy, _)| sx == x && sy == y) {
+            Some(i) if self.sketch[i].2 == cell => {
+                self.sketch.remove(i);
+            }
+            Some(i) => self.sketch[i].2 = cell,
+            None => self.sketch.push((x, y, cell)),
+        }
+    }
+}
+
+fn spawn_message(result: Result<usize, SpawnError>) -> String {
+    match result {
+        Ok(id) => format!("Placed organism #{}", id),
+        Err(SpawnError::Blocked) => "No room there".to_owned(),
+        Err(SpawnError::OutOfBounds) => "Does not fit inside the world".to_owned(),
+    }
+}
+
+impl OrganismManager {
+    /// Applies the current editor tool to the world under the mouse. Called every frame while the editor is active.
+    pub fn update_editor(&mut self) {
+        self.grid.restamp(&self.organisms);
+
+        if is_key_pressed(KeyCode::Escape) {
+            self.editor.active = false;
+            return;
+        }
+        for (i, key) in [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5, KeyCode::Key6, KeyCode::Key7, KeyCode::Key8].iter().enumerate() {
+            if is_key_pressed(*key) {
+                self.editor.tool = EditorTool::ALL[i];
+            }
+        }
+        self.handle_editor_keys();
+
+        let (mouse_x, mouse_y) = mouse_position();
+        if self.over_world_widgets(mouse_x, mouse_y) {
+            return;
+        }
+        let tile = self.camera.tile_at_screen(mouse_x, mouse_y);
+        let pressed = is_mouse_button_pressed(MouseButton::Left);
+        let down = is_mouse_button_down(MouseButton::Left);
+        if !down {
+            self.editor.last_painted = None;
+        }
+
+        match self.editor.tool {
+            EditorTool::Food | EditorTool::Wall | EditorTool::Erase => {
+                if let (true, Some(tile)) = (down, tile) {
+                    self.paint_stroke(tile);
+                }
+            }
+            EditorTool::Place => {
+                if let (true, Some((x, y))) = (pressed, tile) {
+                    if let Some(genome) = self.library.get(self.editor.genome) {
+                        let result = self.spawn_organism(genome.cells.clone(), x, y);
+                        self.editor.message = spawn_message(result);
+                    }
+                }
+            }
+            EditorTool::Sketch => {
+                if let (true, Some((x, y))) = (pressed, tile) {
+                    self.editor.toggle_sketch_cell(x, y);
+                }
+            }
+            EditorTool::Delete => {
+                if let (true, Some(id)) = (pressed, self.grid.organism_at_screen(mouse_x, mouse_y, &self.camera)) {
+                    self.remove_organism(id);
+                    self.editor.message = format!("Removed organism #{}", id);
+                }
+            }
+            EditorTool::Clone => {
+                if let (true, Some(id)) = (pressed, self.grid.organism_at_screen(mouse_x, mouse_y, &self.camera)) {
+                    if let Some(result) = self.clone_organism(id) {
+                        self.editor.message = spawn_message(result);
+                    }
+                }
+            }
+            EditorTool::Region => {
+                if let (true, Some(tile)) = (pressed, tile) {
+                    self.editor.drag_origin = Some(tile);
+                }
+                if let (Some(origin), Some(tile)) = (self.editor.drag_origin, tile) {
+                    let min = (origin.0.min(tile.0), origin.1.min(tile.1));
+                    let max = (origin.0.max(tile.0), origin.1.max(tile.1));
+                    self.editor.region = Some((min, max));
+                }
+                if !down {
+                    self.editor.drag_origin = None;
+                }
+            }
+        }
+    }
+
+    /// [ and ] browse the genome library, G stores the selected organism's anatomy in it,
+    /// C cycles the sketch cell, Enter turns the sketch into an organism and Backspace discards it.
+    fn handle_editor_keys(&mut self) {
+        let library_len = self.library.len().max(1);
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.editor.genome = (self.editor.genome + 1) % library_len;
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.editor.genome = (self.editor.genome + library_len - 1) % library_len;
+        }
+        if is_key_pressed(KeyCode::G) {
+            if let Some(organism) = self.selected_organism() {
+                let (name, cells) = (format!("Species {:08x}", organism.species as u32), organism.genome_cells());
+                self.editor.genome = self.library.add(name, cells);
+                self.editor.tool = EditorTool::Place;
+                self.editor.message = "Added the selected anatomy to the library".to_owned();
+            }
+        }
+        if is_key_pressed(KeyCode::C) {
+            self.editor.sketch_cell = (self.editor.sketch_cell + 1) % Cell::PALETTE.len();
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.editor.sketch.clear();
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            if let Some(&(x, y, _)) = self.editor.sketch.first() {
+                let cells = self.editor.sketch_anatomy((x, y));
+                if !Organism::cells_connected(&cells) {
+                    self.editor.message = "Sketch cells must touch each other".to_owned();
+                } else {
+                    let result = self.spawn_organism(cells, x, y);
+                    if result.is_ok() {
+                        self.editor.sketch.clear();
+                    }
+                    self.editor.message = spawn_message(result);
+                }
+            }
+        }
+        if let (Some(region), true) = (self.editor.region, is_key_pressed(KeyCode::Delete)) {
+            self.apply_region_action(region, RegionAction::Kill);
+        }
+    }
+
+    /// Paints every tile on the line from the previously painted tile, so quick drags leave no gaps.
+    fn paint_stroke(&mut self, (x, y): (usize, usize)) {
+        let (from_x, from_y) = self.editor.last_painted.unwrap_or((x, y));
+        let steps = from_x.abs_diff(x).max(from_y.abs_diff(y)).max(1);
+        for step in 0..=steps {
+            let t = step as f32 / steps as f32;
+            let tile_x = (from_x as f32 + (x as f32 - from_x as f32) * t).round() as usize;
+            let tile_y = (from_y as f32 + (y as f32 - from_y as f32) * t).round() as usize;
+            match self.editor.tool {
+                EditorTool::Food => {
+                    self.grid.place_food(tile_x, tile_y);
+                }
+                EditorTool::Wall => {
+                    self.grid.place_wall(tile_x, tile_y);
+                }
+                _ => self.grid.clear_tile(tile_x, tile_y),
+            }
+        }
+        self.editor.last_painted = Some((x, y));
+    }
+
+    pub fn apply_region_action(&mut self, ((min_x, min_y), (max_x, max_y)): ((usize, usize), (usize, usize)), action: RegionAction) {
+        if action == RegionAction::Kill {
+            let ids = self.organisms_in_region((min_x, min_y), (max_x, max_y));
+            let removed = self.remove_organisms(&ids);
+            self.editor.message = format!("Removed {} organisms", removed);
+            return;
+        }
+
+        for y in min_y..=max_y {
+            for x in min_x..=max_x {
+                match action {
+                    RegionAction::FillFood => {
+                        self.grid.place_food(x, y);
+                    }
+                    RegionAction::FillWalls => {
+                        self.grid.place_wall(x, y);
+                    }
+                    _ => self.grid.clear_tile(x, y),
+                }
+            }
+        }
+    }
+
+    /// Draws the editor toolbar, previews and region outline over the world view. E toggles the editor.
+    pub fn draw_editor_toolbar(&mut self) {
+        if is_key_pressed(KeyCode::E) {
+            self.editor.active = !self.editor.active;
+        }
+        if !self.grid.graphics_on() {
+            return;
+        }
+
+        let (x, y) = Editor::toolbar_origin();
+        if !self.editor.active {
+            if ui::button(x, y, TOOL_WIDTH + 20.0, TOOLBAR_HEIGHT, "Edit (E)", false) {
+                self.editor.active = true;
+            }
+            return;
+        }
+
+        self.draw_editor_previews();
+
+        let mut button_x = x;
+        for tool in EditorTool::ALL {
+            if ui::button(button_x, y, TOOL_WIDTH, TOOLBAR_HEIGHT, tool.label(), self.editor.tool == tool) {
+                self.editor.tool = tool;
+            }
+            button_x += TOOL_WIDTH + GAP;
+        }
+        if ui::button(button_x, y, TOOL_WIDTH, TOOLBAR_HEIGHT, "Done", false) {
+            self.editor.active = false;
+        }
+        button_x += TOOL_WIDTH + GAP;
+        if ui::button(button_x, y, TOOL_WIDTH, TOOLBAR_HEIGHT, "Design", self.designer.open) {
+            self.designer.open = !self.designer.open;
+        }
+        button_x += TOOL_WIDTH + GAP;
+
+        if let (EditorTool::Region, Some(region)) = (self.editor.tool, self.editor.region) {
+            let actions = [("Fill food", RegionAction::FillFood), ("Fill walls", RegionAction::FillWalls), ("Clear", RegionAction::Clear), ("Kill", RegionAction::Kill)];
+            for (label, action) in actions {
+                if ui::button(button_x, y, ACTION_WIDTH, TOOLBAR_HEIGHT, label, false) {
+                    self.apply_region_action(region, action);
+                }
+                button_x += ACTION_WIDTH + GAP;
+            }
+        }
+
+        let hint = match self.editor.tool {
+            EditorTool::Place => format!("Placing: {} ([ ] browse, G adds the selection)", self.library.get(self.editor.genome).map_or("nothing", |genome| genome.name.as_str())),
+            EditorTool::Sketch => format!("Sketching: {:?} (C cell, Enter spawn, Backspace clear)", Cell::PALETTE[self.editor.sketch_cell]),
+            EditorTool::Region => match self.editor.region {
+                Some(((min_x, min_y), (max_x, max_y))) => format!("Region {}x{} (Delete kills)", max_x - min_x + 1, max_y - min_y + 1),
+                None => "Drag to select a region".to_owned(),
+            },
+            tool => format!("{} (drag to paint, 1-8 tools, Esc leaves)", tool.label()),
+        };
+        draw_rectangle(x, y - 26.0, WorldCamera::viewport().0 - 2.0 * x, 22.0, Color::new(0.0, 0.0, 0.0, 0.6));
+        draw_text(format!("{}  {}", hint, self.editor.message).as_str(), x + 4.0, y - 10.0, 20.0, WHITE);
+    }
+
+    fn draw_editor_previews(&self) {
+        let zoom = self.camera.zoom;
+        let ghost = |x: i32, y: i32, cell: &Cell| {
+            if !Grid::in_bounds(x, y) {
+                return;
+            }
+            let (screen_x, screen_y) = self.camera.world_to_screen(x as f32, y as f32);
+            let color = Grid::get_cell_color(cell);
+            draw_rectangle(screen_x, screen_y, zoom, zoom, Color::new(color.r, color.g, color.b, 0.5));
+        };
+
+        for (x, y, cell) in self.editor.sketch.iter() {
+            ghost(*x as i32, *y as i32, cell);
+        }
+
+        let (mouse_x, mouse_y) = mouse_position();
+        if let (EditorTool::Place, Some((x, y)), Some(genome)) = (self.editor.tool, self.camera.tile_at_screen(mouse_x, mouse_y), self.library.get(self.editor.genome)) {
+            for (dx, dy, cell) in genome.cells.iter() {
+                ghost(x as i32 + dx, y as i32 + dy, cell);
+            }
+        }
+
+        if let (EditorTool::Region, Some(((min_x, min_y), (max_x, max_y)))) = (self.editor.tool, self.editor.region) {
+            let (screen_x, screen_y) = self.camera.world_to_screen(min_x as f32, min_y as f32);
+            let width = (max_x - min_x + 1) as f32 * zoom;
+            let height = (max_y - min_y + 1) as f32 * zoom;
+            draw_rectangle_lines(screen_x, screen_y, width, height, 2.0, YELLOW);
+        }
+    }
+}
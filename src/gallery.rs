@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use crate::cell::Cell;
+use crate::charts::species_color;
+use crate::grid::Grid;
+use crate::organism::Organism;
+use crate::ui;
+
+/// What is remembered about a species once its first member appears, even after it dies out.
+pub struct SpeciesRecord {
+    pub anatomy: Vec<(i32, i32, Cell)>, // genome frame anatomy shared by every member
+    pub births: usize, // all-time members, including seeded and placed ones
+    pub first_seen: u128, // frame the first member appeared
+}
+
+/// Every species that has ever lived, keyed by the anatomy hash in `Organism::species`.
+pub struct SpeciesRegistry {
+    records: HashMap<u64, SpeciesRecord>,
+}
+
+impl Default for SpeciesRegistry {
+    fn default() -> Self {
+        SpeciesRegistry::new()
+    }
+}
+
+impl SpeciesRegistry {
+    pub fn new() -> Self {
+        SpeciesRegistry { records: HashMap::new() }
+    }
+
+    pub fn record_birth(&mut self, organism: &Organism, frame: u128) {
+        self.records
+            .entry(organism.species)
+            .or_insert_with(|| SpeciesRecord { anatomy: organism.genome_cells(), births: 0, first_seen: frame })
+            .births += 1;
+    }
+
+    /// Every record as a tab separated `species` line for world snapshots, oldest first.
+    pub fn encode(&self) -> String {
+        let mut records: Vec<(&u64, &SpeciesRecord)> = self.records.iter().collect();
+        records.sort_by_key(|(species, record)| (record.first_seen, **species));
+        records
+            .iter()
+            .map(|(species, record)| format!("species\t{}\t{}\t{}\t{}\n", species, record.births, record.first_seen, Organism::encode_cells(&record.anatomy)))
+            .collect()
+    }
+
+    /// Adds the record of a `species` line. Returns None if the line is malformed.
+    pub fn decode_line(&mut self, fields: &[&str]) -> Option<()> {
+        let ["species", species, births, first_seen, anatomy] = fields else {
+            return None;
+        };
+        let record = SpeciesRecord { anatomy: Organism::decode_anatomy(anatomy), births: births.parse().ok()?, first_seen: first_seen.parse().ok()? };
+        self.records.insert(species.parse().ok()?, record);
+        Some(())
+    }
+
+    pub fn get(&self, species: u64) -> Option<&SpeciesRecord> {
+        self.records.get(&species)
+    }
+
+    /// Living members per species, most populous first. Ties go to the lower hash so the order is stable.
+    pub fn living_counts(organisms: &[Organism]) -> Vec<(u64, usize)> {
+        let mut counts: HashMap<u64, usize> = HashMap::new();
+        for organism in organisms.iter() {
+            *counts.entry(organism.species).or_default() += 1;
+        }
+        let mut counts: Vec<(u64, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+
+    /// Draws the given species as a grid of cards with a thumbnail, live count, births and first-seen frame.
+    /// Returns the species whose card was clicked this frame.
+    pub fn draw_gallery(&self, ranking: &[(u64, usize)], highlighted: Option<u64>, area: Rect, columns: usize) -> Option<u64> {
+        draw_rectangle(area.x, area.y, area.w, area.h, WHITE);
+        let rows = ranking.len().div_ceil(columns).max(1);
+        let card_width = area.w / columns as f32;
+        let card_height = area.h / rows as f32;
+        let thumbnail_height = card_height - 48.0;
+        let mut clicked = None;
+
+        for (i, (species, living)) in ranking.iter().enumerate() {
+            let Some(record) = self.records.get(species) else {
+                continue;
+            };
+            let card_x = area.x + (i % columns) as f32 * card_width;
+            let card_y = area.y + (i / columns) as f32 * card_height;
+
+            let (min_x, max_x) = record.anatomy.iter().fold((0, 0), |(min, max), (x, _, _)| (min.min(*x), max.max(*x)));
+            let (min_y, max_y) = record.anatomy.iter().fold((0, 0), |(min, max), (_, y, _)| (min.min(*y), max.max(*y)));
+            let span = (max_x - min_x + 1).max(max_y - min_y + 1) as f32;
+            let tile = (thumbnail_height.min(card_width) * 0.8 / span).min(10.0);
+            Grid::draw_anatomy(&record.anatomy, card_x + card_width / 2.0, card_y + thumbnail_height / 2.0, tile);
+
+            draw_rectangle(card_x + 4.0, card_y + thumbnail_height, 8.0, 8.0, species_color(*species));
+            let lines = [format!("#{} live {}", i + 1, living), format!("births {}", record.births), format!("since {}", record.first_seen)];
+            for (line_index, line) in lines.iter().enumerate() {
+                draw_text(line.as_str(), card_x + 4.0, card_y + thumbnail_height + 14.0 + line_index as f32 * 14.0, 16.0, BLACK);
+            }
+
+            let outline = if highlighted == Some(*species) { RED } else { LIGHTGRAY };
+            draw_rectangle_lines(card_x, card_y, card_width, card_height, 2.0, outline);
+            if ui::button_area(Rect::new(card_x, card_y, card_width, card_height)) {
+                clicked = Some(*species);
+            }
+        }
+        clicked
+    }
+}
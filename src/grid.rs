@@ -1,59 +1,191 @@
 use crate::organism::Organism;
 
 use crate::{Direction, cell::Cell, organism_manager::SimData};
+use crate::senses::{EyeReading, Seen};
+use crate::camera::WorldCamera;
+use crate::layout::{Layout, LINE_HEIGHT, BUTTON_HEIGHT};
+use std::collections::HashMap;
 use macroquad::prelude::*;
-use ::rand::{SeedableRng, Rng};
+use ::rand::{SeedableRng, Rng, seq::SliceRandom};
 use ::rand::rngs::StdRng;
 
 use crate::utils::*;//{WIDTH, HEIGHT, CELL_SIZE, PRODUCER_RATE}; 
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpawnError {
+    OutOfBounds, // every candidate position left the world
+    Blocked, // candidates inside the world were all overlapping other organisms
+}
+
+/// Summary of the organism covering a tile, used by eyes to tell organisms apart.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Occupant {
+    pub id: usize,
+    pub species: u64,
+    pub plant: bool,
+    pub killer: bool,
+}
+
+impl Occupant {
+    pub fn of(organism: &Organism) -> Self {
+        let has = |target: Cell| organism.cells.iter().any(|(_, _, cell)| *cell == target);
+        Occupant {
+            id: organism.id,
+            species: organism.species,
+            plant: has(Cell::Producer) && !has(Cell::Mover),
+            killer: has(Cell::Killer),
+        }
+    }
+}
+
 enum CellContent {
     Empty,
     Food,
-    Organism,
+    Wall,
+    Organism(Occupant),
 }
 
 pub struct Grid {
     pub rng: StdRng,
     pub foods: [[bool; WIDTH]; HEIGHT],
+    pub walls: [[bool; WIDTH]; HEIGHT],
     pub organs: [[Cell; WIDTH]; HEIGHT],
+    pub occupants: [[Option<Occupant>; WIDTH]; HEIGHT],
     pending_kill_coordinates: Vec<(usize, usize)>, // x, y
     pending_kill_killers: Vec<usize>, // id
     graphics_on: bool,
 }
 
+impl Default for Grid {
+    fn default() -> Self {
+        Grid::new()
+    }
+}
+
 impl Grid {
     pub fn new() -> Self {
+        Grid::with_seed(SEED)
+    }
+
+    /// A grid whose food placement is driven by `seed`, offset from the simulation's own stream.
+    pub fn with_seed(seed: u64) -> Self {
         Grid {
-            rng: StdRng::seed_from_u64(SEED+1),
+            rng: StdRng::seed_from_u64(seed.wrapping_add(1)),
             foods: [[false; WIDTH]; HEIGHT],
+            walls: [[false; WIDTH]; HEIGHT],
             organs: [[Cell::Empty; WIDTH]; HEIGHT],
+            occupants: [[None; WIDTH]; HEIGHT],
             pending_kill_coordinates: Vec::new(),
             pending_kill_killers: Vec::new(),
             graphics_on: true,
         }
     }
 
+    /// Food, walls and attacks waiting to land as tab separated lines for world snapshots: one `tiles` line per
+    /// row, with `.` empty, `f` food, `w` wall and `b` both, then a `kill` line per pending attack. Organisms are
+    /// stored with themselves and stamped back when loading.
+    pub fn encode(&self) -> String {
+        let mut text = String::new();
+        for y in 0..HEIGHT {
+            text.push_str(&format!("tiles\t{}\t", y));
+            text.extend((0..WIDTH).map(|x| match (self.foods[y][x], self.walls[y][x]) {
+                (false, false) => '.',
+                (true, false) => 'f',
+                (false, true) => 'w',
+                (true, true) => 'b',
+            }));
+            text.push('\n');
+        }
+        for ((x, y), killer) in self.pending_kill_coordinates.iter().zip(self.pending_kill_killers.iter()) {
+            text.push_str(&format!("kill\t{}\t{}\t{}\n", x, y, killer));
+        }
+        text
+    }
+
+    /// Applies a `tiles` or `kill` line written by `encode`. Returns None if the line is malformed.
+    pub fn decode_line(&mut self, fields: &[&str]) -> Option<()> {
+        match fields {
+            ["tiles", y, row] => {
+                let y: usize = y.parse().ok()?;
+                if y >= HEIGHT || row.chars().count() != WIDTH {
+                    return None;
+                }
+                for (x, tile) in row.chars().enumerate() {
+                    (self.foods[y][x], self.walls[y][x]) = match tile {
+                        '.' => (false, false),
+                        'f' => (true, false),
+                        'w' => (false, true),
+                        'b' => (true, true),
+                        _ => return None,
+                    };
+                }
+            }
+            ["kill", x, y, killer] => {
+                self.pending_kill_coordinates.push((x.parse().ok()?, y.parse().ok()?));
+                self.pending_kill_killers.push(killer.parse().ok()?);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    pub fn graphics_on(&self) -> bool {
+        self.graphics_on
+    }
+
     pub fn scatter_food(&mut self) {
         for y in 0..HEIGHT {
             for x in 0..WIDTH {
-                if self.rng.gen::<f32>() < 0.1 {
+                if self.rng.gen::<f32>() < 0.1 && !self.walls[y][x] {
                     self.foods[y][x] = true;
                 }
             }
         }
     }
 
-    pub fn produce_food(&mut self, x: usize, y: usize) {
-        if self.rng.gen::<f32>() > PRODUCER_RATE {
+    pub fn produce_food(&mut self, x: usize, y: usize, producer_rate: f32) {
+        if self.rng.gen::<f32>() > producer_rate {
             return;
         }
 
-        self.foods[
-            ((if self.rng.gen_bool(0.5) { 1 } else { -1 }) as i32 + y as i32).clamp(0, (HEIGHT - 1) as i32) as usize
-        ][
-            ((if self.rng.gen_bool(0.5) { 1 } else { -1 }) as i32 + x as i32).clamp(0, (WIDTH - 1) as i32) as usize
-        ] = true;
+        let food_y = (if self.rng.gen_bool(0.5) { 1 } else { -1 } + y as i32).clamp(0, (HEIGHT - 1) as i32) as usize;
+        let food_x = (if self.rng.gen_bool(0.5) { 1 } else { -1 } + x as i32).clamp(0, (WIDTH - 1) as i32) as usize;
+        if !self.walls[food_y][food_x] {
+            self.foods[food_y][food_x] = true;
+        }
+    }
+
+    pub fn food_count(&self) -> usize {
+        self.foods.iter().map(|row| row.iter().filter(|food| **food).count()).sum()
+    }
+
+    pub fn in_bounds(x: i32, y: i32) -> bool {
+        x >= 0 && y >= 0 && x < WIDTH as i32 && y < HEIGHT as i32
+    }
+
+    /// Puts food on a tile that is not a wall. Returns false if nothing changed.
+    pub fn place_food(&mut self, x: usize, y: usize) -> bool {
+        if self.walls[y][x] || self.foods[y][x] {
+            return false;
+        }
+        self.foods[y][x] = true;
+        true
+    }
+
+    /// Builds a wall on a tile no organism covers, removing any food there. Returns false if nothing changed.
+    pub fn place_wall(&mut self, x: usize, y: usize) -> bool {
+        if self.walls[y][x] || self.occupants[y][x].is_some() {
+            return false;
+        }
+        self.walls[y][x] = true;
+        self.foods[y][x] = false;
+        true
+    }
+
+    /// Removes food and walls from a tile. Organisms are left alone.
+    pub fn clear_tile(&mut self, x: usize, y: usize) {
+        self.walls[y][x] = false;
+        self.foods[y][x] = false;
     }
 
     pub fn is_cell_empty(&self, x: usize, y: usize) -> bool {
@@ -61,19 +193,19 @@ impl Grid {
             return false;
         }
     
-        if self.organs[y][x] != Cell::Empty {
+        if self.organs[y][x] != Cell::Empty || self.walls[y][x] {
             return false;
         }
     
         true
     }
     fn cell_contents(&self, x: usize, y: usize) -> CellContent {
-        if x >= WIDTH || y >= HEIGHT {
-            return CellContent::Empty;
+        if x >= WIDTH || y >= HEIGHT || self.walls[y][x] {
+            return CellContent::Wall;
         }
     
-        if self.organs[y][x] != Cell::Empty {
-            return CellContent::Organism;
+        if let Some(occupant) = self.occupants[y][x] {
+            return CellContent::Organism(occupant);
         }
     
         if self.foods[y][x] {
@@ -83,9 +215,9 @@ impl Grid {
         CellContent::Empty
     }
 
-    pub fn make_remains(&mut self, organism: &Organism) {
+    pub fn make_remains(&mut self, organism: &Organism, drop_food_rate: f32) {
         for (dx, dy, _) in organism.cells.iter() {
-            if self.rng.gen::<f32>() > DROP_FOOD_RATE {
+            if self.rng.gen::<f32>() > drop_food_rate {
                 continue;
             }
 
@@ -96,7 +228,9 @@ impl Grid {
             }
             let x = x as usize;
             let y = y as usize;
-            self.foods[y][x] = true;
+            if !self.walls[y][x] {
+                self.foods[y][x] = true;
+            }
         }
     }
 
@@ -105,66 +239,114 @@ impl Grid {
         self.pending_kill_killers.push(id);
     }
 
-    pub fn get_eye_data(&self, x: usize, y: usize, dir: Direction) -> f32 {
-        fn increment_pos(x: usize, y:usize, dir: Direction) -> (usize, usize) {
-            match dir {
-                Direction::Up => (x, y - 1),
-                Direction::Down => (x, y + 1),
-                Direction::Left => (x - 1, y),
-                Direction::Right => (x + 1, y),
-                _ => (x, y),
+    /// Number of pending attacks by other organisms that reach the tile. An attack reaches the four tiles next to
+    /// the killer cell, including those in the first row and column of the world.
+    fn attacks_on(&self, x: usize, y: usize, id: usize) -> usize {
+        self.pending_kill_coordinates
+            .iter()
+            .zip(self.pending_kill_killers.iter())
+            .filter(|(&(kill_x, kill_y), &killer)| x.abs_diff(kill_x) + y.abs_diff(kill_y) == 1 && killer != id)
+            .count()
+    }
+
+    /// Casts a ray from the eye at (x, y) and reports the first thing it hits within `MAX_EYE_DIST` tiles.
+    /// The ray passes through the viewer's own cells.
+    pub fn get_eye_data(&self, x: usize, y: usize, dir: Direction, viewer_id: usize, viewer_species: u64) -> EyeReading {
+        let mut cx = x as i32;
+        let mut cy = y as i32;
+
+        for distance in 1..=MAX_EYE_DIST {
+            cx += dir.x_offset() as i32;
+            cy += dir.y_offset() as i32;
+            if cx < 0 || cy < 0 {
+                return EyeReading { seen: Seen::Wall, distance };
             }
-        }
-        
-        let mut data = 0.1;
 
-        let mut depth = 0;
+            let seen = match self.cell_contents(cx as usize, cy as usize) {
+                CellContent::Empty => continue,
+                CellContent::Organism(occupant) if occupant.id == viewer_id => continue,
+                CellContent::Food => Seen::Food,
+                CellContent::Wall => Seen::Wall,
+                CellContent::Organism(occupant) if occupant.species == viewer_species => Seen::Kin,
+                CellContent::Organism(occupant) if occupant.killer => Seen::Killer,
+                CellContent::Organism(occupant) if occupant.plant => Seen::Plant,
+                CellContent::Organism(_) => Seen::Organism,
+            };
+            return EyeReading { seen, distance };
+        }
 
-        let mut cx = x;
-        let mut cy = y;
+        EyeReading { seen: Seen::Nothing, distance: MAX_EYE_DIST }
+    }
 
-        loop {
-            if cx == 0 || cy == 0 || cx >= WIDTH || cy >= HEIGHT {
-                break;
+    /// Checks whether the organism's cells fit on free tiles when anchored at (x, y).
+    pub fn check_spawn(&self, organism: &Organism, x: i32, y: i32) -> Result<(), SpawnError> {
+        for (dx, dy, _) in organism.cells.iter() {
+            let cx = x + dx;
+            let cy = y + dy;
+            if cx < 0 || cy < 0 || cx >= WIDTH as i32 || cy >= HEIGHT as i32 {
+                return Err(SpawnError::OutOfBounds);
             }
+            if !self.is_cell_empty(cx as usize, cy as usize) {
+                return Err(SpawnError::Blocked);
+            }
+        }
+        Ok(())
+    }
 
-            (cx, cy) = increment_pos(cx, cy, dir);
-
-            match self.cell_contents(cx, cy) {
-                CellContent::Food => {
-                    data = 0.5;
-                    break;
-                }
-                CellContent::Organism => {
-                    data = -1.0;
-                    break;
+    /// Searches rings of growing radius around (x, y) for a free anchor, starting just outside the organism's own body size.
+    pub fn find_spawn(&self, organism: &Organism, x: usize, y: usize, rng: &mut StdRng) -> Result<(usize, usize), SpawnError> {
+        let (width, height) = organism.body_range();
+        let min_radius = width.max(height) as i32;
+        let mut error = SpawnError::OutOfBounds;
+
+        for radius in min_radius..=min_radius + SPAWN_SEARCH_RADIUS {
+            let mut ring: Vec<(i32, i32)> = (-radius..=radius)
+                .flat_map(|dx| (-radius..=radius).map(move |dy| (dx, dy)))
+                .filter(|(dx, dy)| dx.abs().max(dy.abs()) == radius)
+                .collect();
+            ring.shuffle(rng);
+
+            for (dx, dy) in ring {
+                let (spawn_x, spawn_y) = (x as i32 + dx, y as i32 + dy);
+                match self.check_spawn(organism, spawn_x, spawn_y) {
+                    Ok(()) => return Ok((spawn_x as usize, spawn_y as usize)),
+                    Err(SpawnError::Blocked) => error = SpawnError::Blocked,
+                    Err(SpawnError::OutOfBounds) => {}
                 }
-                _ => {}
             }
+        }
 
-            depth+=1;
-            if depth >= MAX_EYE_DIST {
-                break;
-            }
+        Err(error)
+    }
+
+    /// Rebuilds the organ and occupant maps from the organisms' current positions, without resolving attacks.
+    pub fn restamp(&mut self, organisms: &[Organism]) {
+        self.organs = [[Cell::Empty; WIDTH]; HEIGHT];
+        self.occupants = [[None; WIDTH]; HEIGHT];
+        for organism in organisms.iter() {
+            self.occupy(organism);
         }
+    }
 
-        data
+    /// Marks the organism's tiles as taken so later spawns in the same frame cannot overlap it.
+    pub fn occupy(&mut self, organism: &Organism) {
+        let occupant = Occupant::of(organism);
+        for (dx, dy, cell) in organism.cells.iter() {
+            let x = (organism.x as i32 + dx) as usize;
+            let y = (organism.y as i32 + dy) as usize;
+            self.organs[y][x] = *cell;
+            self.occupants[y][x] = Some(occupant);
+        }
     }
 
-    pub fn check_spawn(&self, organism: &Organism) -> bool {
+    /// Clears the organism's tiles, the counterpart of `occupy`.
+    pub fn vacate(&mut self, organism: &Organism) {
         for (dx, dy, _) in organism.cells.iter() {
-            let x = organism.x as i32 + dx;
-            let y = organism.y as i32 + dy;
-            if x < 0 || y < 0 {
-                return false;
-            }
-            let x = x as usize;
-            let y = y as usize;
-            if !self.is_cell_empty(x, y) || x >= WIDTH || y >= HEIGHT {
-                return false;
-            }
+            let x = (organism.x as i32 + dx) as usize;
+            let y = (organism.y as i32 + dy) as usize;
+            self.organs[y][x] = Cell::Empty;
+            self.occupants[y][x] = None;
         }
-        true
     }
 
     pub fn mouth_eat(&mut self, x: usize, y: usize) -> bool {
@@ -191,44 +373,33 @@ impl Grid {
         ((WIDTH as f32 * CELL_SIZE + MENU_WIDTH) as i32, (HEIGHT as f32 * CELL_SIZE) as i32)
     }
 
-    pub fn update(&mut self, organisms: &mut Vec<Organism>) {
+    pub fn update(&mut self, organisms: &mut [Organism], damage_penalty: f32) {
         self.organs = [[Cell::Empty; WIDTH]; HEIGHT];
+        self.occupants = [[None; WIDTH]; HEIGHT];
         for organism in organisms.iter_mut() {
+            let occupant = Occupant::of(organism);
             for cell in organism.cells.iter() {
                 let x = (organism.x as i32 + cell.0) as usize;
                 let y = (organism.y as i32 + cell.1) as usize;
 
-                if cell.2 != Cell::Armor {
-                    for (i, (kill_x, kill_y)) in self.pending_kill_coordinates.iter().enumerate() {
-                        if *kill_x <= 1 || *kill_y <= 1 {
-                            if (x == *kill_x + 1 && y == *kill_y ||
-                                x == *kill_x && y == *kill_y + 1) &&
-                                self.pending_kill_killers[i] != organism.id {
-                                organism.killed = true;
-                                continue;
-                            }
-                        } else {
-                            if (x == *kill_x + 1 && y == *kill_y ||
-                                x == *kill_x - 1 && y == *kill_y ||
-                                x == *kill_x && y == *kill_y + 1 ||
-                                x == *kill_x && y == *kill_y - 1) &&
-                                self.pending_kill_killers[i] != organism.id {
-                                organism.killed = true;
-                                continue;
-                            }
-                        } 
+                let attacks = self.attacks_on(x, y, organism.id);
+                if attacks > 0 {
+                    if cell.2 == Cell::Armor {
+                        organism.reward -= damage_penalty * attacks as f32;
+                    } else {
+                        organism.killed = true;
                     }
                 }
 
                 self.organs[y][x] = cell.2;
+                self.occupants[y][x] = Some(occupant);
             }
         }
         self.pending_kill_coordinates.clear();
         self.pending_kill_killers.clear();
-        if crate::utils::GRAPHICS {self.draw();}
     }
 
-    fn get_cell_color(cell: &Cell) -> Color {
+    pub fn get_cell_color(cell: &Cell) -> Color {
         match cell {
             Cell::Empty => DARKGRAY,
             Cell::Body => WHITE,
@@ -243,24 +414,26 @@ impl Grid {
         }
     }
 
-    pub fn update_sim_menu(&mut self, sim_data: &SimData) {
-        draw_rectangle(WIDTH as f32 * CELL_SIZE, 0.0, MENU_WIDTH, MENU_HEIGHT, LIGHTGRAY);
-        draw_rectangle(WIDTH as f32 * CELL_SIZE + CELL_SIZE, CELL_SIZE, MENU_WIDTH - 2.0 * CELL_SIZE, MENU_WIDTH - 2.0 * CELL_SIZE, WHITE);
-        self.draw_success(sim_data.best_species.as_str());
-
-        let mut text = format!("Organism #: {}", sim_data.organism_num);
-        draw_text(&text, WIDTH as f32 * CELL_SIZE + CELL_SIZE * 2.0, MENU_HEIGHT / 1.75, 20.0, BLACK);
-        text = format!("Hunger Deaths: {}", sim_data.hunger_death);
-        draw_text(&text, WIDTH as f32 * CELL_SIZE + CELL_SIZE * 2.0, MENU_HEIGHT / 1.75 + 25.0, 20.0, BLACK);
-        text = format!("Age Deaths: {}", sim_data.age_death);
-        draw_text(&text, WIDTH as f32 * CELL_SIZE + CELL_SIZE * 2.0, MENU_HEIGHT / 1.75 + 50.0, 20.0, BLACK);
+    pub fn update_sim_menu(&mut self, sim_data: &SimData, layout: &Layout) {
+        draw_rectangle(layout.menu.x, layout.menu.y, layout.menu.w, layout.lower.y - layout.menu.y, LIGHTGRAY);
+
+        let lines = [
+            format!("Organism #: {}", sim_data.organism_num),
+            format!("Hunger Deaths: {}", sim_data.hunger_death),
+            format!("Age Deaths: {}", sim_data.age_death),
+            format!("Mean Mutation Rate: {:.3}", sim_data.mean_mutation_rate),
+            format!("Failed Births: {} blocked, {} edge", sim_data.blocked_births, sim_data.out_of_bounds_births),
+        ];
+        for (i, text) in lines.iter().enumerate() {
+            draw_text(text.as_str(), layout.stats.x, layout.stats.y + 20.0 + i as f32 * LINE_HEIGHT, 20.0, BLACK);
+        }
 
-        let button_x = WIDTH as f32 * CELL_SIZE + CELL_SIZE * 2.0;
-        let button_y = MENU_HEIGHT / 1.5 + 75.0;
+        let button_x = layout.controls.x;
+        let button_y = layout.controls.y;
         let button_width = if self.graphics_on {190.0} else {200.0};
-        let button_height = 30.0;
+        let button_height = BUTTON_HEIGHT;
         draw_rectangle(button_x, button_y, button_width, button_height, GRAY);
-        draw_text(&format!("Toggle Graphics: {}", self.graphics_on), button_x + 5.0, button_y + 20.0, 20.0, BLACK);
+        draw_text(format!("Toggle Graphics: {}", self.graphics_on).as_str(), button_x + 5.0, button_y + 20.0, 20.0, BLACK);
 
         if is_mouse_button_pressed(MouseButton::Left) {
             let (mouse_x, mouse_y) = mouse_position();
@@ -270,62 +443,175 @@ impl Grid {
         }
     }
 
-    fn draw_success(&self, success_org: &str) {
-        let decoded_cells = Organism::decode_anatomy(success_org);
-        if decoded_cells.is_empty() {
+    /// Draws an anatomy centred on (center_x, center_y) with square tiles of `tile_size`.
+    pub fn draw_anatomy(cells: &[(i32, i32, Cell)], center_x: f32, center_y: f32, tile_size: f32) {
+        if cells.is_empty() {
             return;
         }
 
-        let min_x = decoded_cells.iter().map(|(x, _, _)| *x).min().unwrap_or(0);
-        let max_x = decoded_cells.iter().map(|(x, _, _)| *x).max().unwrap_or(0);
-        let min_y = decoded_cells.iter().map(|(_, y, _)| *y).min().unwrap_or(0);
-        let max_y = decoded_cells.iter().map(|(_, y, _)| *y).max().unwrap_or(0);
+        let min_x = cells.iter().map(|(x, _, _)| *x).min().unwrap_or(0);
+        let max_x = cells.iter().map(|(x, _, _)| *x).max().unwrap_or(0);
+        let min_y = cells.iter().map(|(_, y, _)| *y).min().unwrap_or(0);
+        let max_y = cells.iter().map(|(_, y, _)| *y).max().unwrap_or(0);
 
-        let organism_width = (max_x - min_x + 1) as f32 * CELL_SIZE;
-        let organism_height = (max_y - min_y + 1) as f32 * CELL_SIZE;
-
-        let center_x = WIDTH as f32 * CELL_SIZE + MENU_WIDTH / 2.0;
-        let center_y = MENU_WIDTH / 2.0;
+        let organism_width = (max_x - min_x + 1) as f32 * tile_size;
+        let organism_height = (max_y - min_y + 1) as f32 * tile_size;
 
         let start_x = center_x - organism_width / 2.0;
         let start_y = center_y - organism_height / 2.0;
 
-        for (dx, dy, cell) in decoded_cells.iter() {
-            let x = start_x + (*dx - min_x) as f32 * CELL_SIZE;
-            let y = start_y + (*dy - min_y) as f32 * CELL_SIZE;
+        for (dx, dy, cell) in cells.iter() {
+            let x = start_x + (*dx - min_x) as f32 * tile_size;
+            let y = start_y + (*dy - min_y) as f32 * tile_size;
 
-            draw_rectangle(x, y, CELL_SIZE, CELL_SIZE, Grid::get_cell_color(cell));
+            draw_rectangle(x, y, tile_size, tile_size, Grid::get_cell_color(cell));
         }
     }
 
-    pub fn draw(&self) {
+    /// Outlines the organism's bounding box on the world view.
+    pub fn draw_highlight(&self, organism: &Organism, camera: &WorldCamera, color: Color) {
         if !self.graphics_on {return;}
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                let mut extra_rect: Direction = Direction::None;
-                
-                let color = match self.organs[y][x] {
-                    Cell::Empty => {if self.foods[y][x] {BLUE} else {DARKGRAY}},
-                    Cell::Body => WHITE,
-
-                    Cell::Mouth => ORANGE,
-                    Cell::Producer => GREEN,
-                    Cell::Mover => LIGHTGRAY,
-                    Cell::Killer => RED,
-                    Cell::Armor => YELLOW,
-                    Cell::Eye(eye_dir) => {
-                        extra_rect = eye_dir.to_direction();
-                        PURPLE},
-                    Cell::Brain => PINK,
-                };
-
-                draw_rectangle(x as f32 * CELL_SIZE, y as f32 * CELL_SIZE, CELL_SIZE, CELL_SIZE, color);
+        let (width, height) = organism.body_range();
+        let left = organism.x as f32 - organism.body_offset().0 as f32;
+        let top = organism.y as f32 - organism.body_offset().1 as f32;
+        let (screen_x, screen_y) = camera.world_to_screen(left - 1.0, top - 1.0);
+        draw_rectangle_lines(screen_x, screen_y, (width as f32 + 2.0) * camera.zoom, (height as f32 + 2.0) * camera.zoom, 2.0, color);
+    }
+
+    /// Id of the organism covering the tile under a screen position, if any.
+    pub fn organism_at_screen(&self, screen_x: f32, screen_y: f32, camera: &WorldCamera) -> Option<usize> {
+        let (x, y) = camera.tile_at_screen(screen_x, screen_y)?;
+        self.occupants[y][x].map(|occupant| occupant.id)
+    }
+
+    /// Colour of a tile, with an organism's colour from `organism_colors` taking precedence over its cell colour,
+    /// and the direction an eye on the tile looks, if any.
+    pub fn tile_color(&self, x: usize, y: usize, organism_colors: &HashMap<usize, Color>) -> (Color, Direction) {
+        let (color, eye) = match self.organs[y][x] {
+            Cell::Empty => (if self.walls[y][x] {BROWN} else if self.foods[y][x] {BLUE} else {DARKGRAY}, Direction::None),
+            Cell::Eye(eye_dir) => (PURPLE, eye_dir.to_direction()),
+            cell => (Grid::get_cell_color(&cell), Direction::None),
+        };
+        let color = self.occupants[y][x].and_then(|occupant| organism_colors.get(&occupant.id)).copied().unwrap_or(color);
+        (color, eye)
+    }
+
+    /// Draws the tiles visible through the camera. Organisms with an entry in `organism_colors` are drawn in that
+    /// colour instead of by cell type.
+    pub fn draw(&self, camera: &WorldCamera, organism_colors: &HashMap<usize, Color>) {
+        if !self.graphics_on {return;}
+        let ((min_x, max_x), (min_y, max_y)) = camera.visible_tiles();
+        let tile = camera.zoom;
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let (color, extra_rect) = self.tile_color(x, y, organism_colors);
+                let (screen_x, screen_y) = camera.world_to_screen(x as f32, y as f32);
+                draw_rectangle(screen_x, screen_y, tile, tile, color);
                 if extra_rect != Direction::None {
-                    draw_line(CELL_SIZE * (x as f32 + 0.5), CELL_SIZE * (y as f32 + 0.5), 
-                    CELL_SIZE * (x as f32 + 0.5 * (1.0 + extra_rect.x_offset())), 
-                    CELL_SIZE * (y as f32 + 0.5 * (1.0 + extra_rect.y_offset())), 4.0, BLACK);
+                    draw_line(screen_x + tile * 0.5, screen_y + tile * 0.5, 
+                    screen_x + tile * 0.5 * (1.0 + extra_rect.x_offset()), 
+                    screen_y + tile * 0.5 * (1.0 + extra_rect.y_offset()), 0.4 * tile, BLACK);
                 }
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::innovation::InnovationTracker;
+    use crate::params::SimParams;
+
+    fn organism(x: usize, y: usize) -> Organism {
+        let cells = vec![(-1, -1, Cell::Mover), (0, 0, Cell::Mouth), (1, 1, Cell::Producer)];
+        Organism::new(x, y, cells, 0, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1))
+    }
+
+    fn fits(organism: &Organism, (x, y): (usize, usize)) -> bool {
+        organism.cells.iter().all(|(dx, dy, _)| {
+            let (cx, cy) = (x as i32 + dx, y as i32 + dy);
+            cx >= 0 && cy >= 0 && cx < WIDTH as i32 && cy < HEIGHT as i32
+        })
+    }
+
+    #[test]
+    fn find_spawn_stays_inside_the_world_at_every_corner() {
+        let grid = Grid::new();
+        let mut rng = StdRng::seed_from_u64(2);
+        for (x, y) in [(1, 1), (WIDTH - 2, 1), (1, HEIGHT - 2), (WIDTH - 2, HEIGHT - 2)] {
+            let parent = organism(x, y);
+            let position = grid.find_spawn(&parent, x, y, &mut rng).expect("an empty world has room next to every corner");
+            assert!(fits(&parent, position), "{:?} puts cells outside the world", position);
+            assert!(position.0.abs_diff(x).max(position.1.abs_diff(y)) >= 3, "{:?} overlaps the parent's footprint", position);
+        }
+    }
+
+    #[test]
+    fn check_spawn_rejects_cells_left_of_or_above_the_world() {
+        let grid = Grid::new();
+        let organism = organism(0, 0);
+        assert_eq!(grid.check_spawn(&organism, 0, 0), Err(SpawnError::OutOfBounds));
+        assert_eq!(grid.check_spawn(&organism, -5, 10), Err(SpawnError::OutOfBounds));
+        assert_eq!(grid.check_spawn(&organism, 10, -5), Err(SpawnError::OutOfBounds));
+        assert_eq!(grid.check_spawn(&organism, 1, 1), Ok(()));
+    }
+
+    #[test]
+    fn find_spawn_near_the_origin_does_not_underflow() {
+        let grid = Grid::new();
+        let parent = organism(0, 0);
+        let position = grid.find_spawn(&parent, 0, 0, &mut StdRng::seed_from_u64(3)).expect("there is room below and to the right");
+        assert!(fits(&parent, position));
+    }
+
+    #[test]
+    fn find_spawn_tells_blocked_from_out_of_bounds() {
+        let mut grid = Grid::new();
+        grid.organs = [[Cell::Armor; WIDTH]; HEIGHT];
+        let parent = organism(WIDTH / 2, HEIGHT / 2);
+        assert_eq!(grid.find_spawn(&parent, WIDTH / 2, HEIGHT / 2, &mut StdRng::seed_from_u64(4)), Err(SpawnError::Blocked));
+
+        let wide = Organism::new(0, 0, (0..WIDTH as i32 + 1).map(|x| (x, 0, Cell::Mouth)).collect(), 0, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1));
+        assert_eq!(Grid::new().find_spawn(&wide, 0, 0, &mut StdRng::seed_from_u64(5)), Err(SpawnError::OutOfBounds));
+    }
+
+    #[test]
+    fn attacks_next_to_the_top_and_left_edges_reach_all_four_neighbours() {
+        for (killer, target) in [((1, 5), (0, 5)), ((5, 1), (5, 0)), ((1, 1), (0, 1)), ((1, 1), (1, 0)), ((0, 0), (1, 0)), ((0, 0), (0, 1))] {
+            let mut grid = Grid::new();
+            grid.killer_activates(killer.0, killer.1, 0);
+            assert_eq!(grid.attacks_on(target.0, target.1, 1), 1, "attack from {:?} missed {:?}", killer, target);
+            assert_eq!(grid.attacks_on(target.0, target.1, 0), 0, "killers do not hit themselves");
+        }
+
+        let mut grid = Grid::new();
+        grid.killer_activates(1, 1, 0);
+        assert_eq!(grid.attacks_on(0, 0, 1) + grid.attacks_on(2, 2, 1) + grid.attacks_on(1, 1, 1), 0, "diagonal and own tiles are out of reach");
+    }
+
+    #[test]
+    fn eyes_see_past_the_viewer_own_cells() {
+        use crate::cell::EyeType;
+        use crate::senses::Seen;
+
+        let mut grid = Grid::new();
+        let cells = vec![(0, 0, Cell::Eye(EyeType::Right)), (1, 0, Cell::Mouth), (2, 0, Cell::Mover)];
+        let viewer = Organism::new(10, 10, cells, 0, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1));
+        let other = Organism::new(14, 10, vec![(0, 0, Cell::Producer)], 1, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1));
+        grid.occupy(&viewer);
+        grid.occupy(&other);
+
+        let reading = grid.get_eye_data(10, 10, Direction::Right, viewer.id, viewer.species);
+        assert_eq!((reading.seen, reading.distance), (Seen::Plant, 4));
+
+        // another organism of the same species is not looked through
+        let reading = grid.get_eye_data(10, 10, Direction::Right, 7, viewer.species);
+        assert_eq!((reading.seen, reading.distance), (Seen::Kin, 1));
+
+        grid.vacate(&other);
+        grid.foods[10][13] = true;
+        let reading = grid.get_eye_data(10, 10, Direction::Right, viewer.id, viewer.species);
+        assert_eq!((reading.seen, reading.distance), (Seen::Food, 3));
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+use crate::brain::OUTPUT_NUM;
+use crate::senses::InputKey;
+use crate::utils::MEMORY_NEURON_NUM;
+
+/// Nodes that exist independently of mutation history, so every genome agrees on their ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NodeKey {
+    Input(InputKey),
+    Output(usize),
+    Memory(usize),
+}
+
+impl NodeKey {
+    pub fn encode(&self) -> String {
+        match self {
+            NodeKey::Input(key) => format!("input:{}", key.encode()),
+            NodeKey::Output(index) => format!("output:{}", index),
+            NodeKey::Memory(index) => format!("memory:{}", index),
+        }
+    }
+
+    pub fn decode(encoded: &str) -> Option<NodeKey> {
+        match encoded.split_once(':')? {
+            ("input", key) => InputKey::decode(key).map(NodeKey::Input),
+            ("output", index) => index.parse().ok().filter(|index| *index < OUTPUT_NUM).map(NodeKey::Output),
+            ("memory", index) => index.parse().ok().filter(|index| *index < MEMORY_NEURON_NUM).map(NodeKey::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Hands out node ids and innovation numbers shared by the whole population, so the same
+/// structural mutation in two lineages gets the same number.
+pub struct InnovationTracker {
+    next_node: usize,
+    next_innovation: usize,
+    fixed_nodes: HashMap<NodeKey, usize>,
+    connections: HashMap<(usize, usize), usize>,
+    splits: HashMap<usize, usize>, // split connection innovation -> node id
+}
+
+impl Default for InnovationTracker {
+    fn default() -> Self {
+        InnovationTracker::new()
+    }
+}
+
+impl InnovationTracker {
+    pub fn new() -> Self {
+        InnovationTracker {
+            next_node: 0,
+            next_innovation: 0,
+            fixed_nodes: HashMap::new(),
+            connections: HashMap::new(),
+            splits: HashMap::new(),
+        }
+    }
+
+    pub fn fixed_node(&mut self, key: NodeKey) -> usize {
+        if let Some(id) = self.fixed_nodes.get(&key) {
+            return *id;
+        }
+        let id = self.new_node();
+        self.fixed_nodes.insert(key, id);
+        id
+    }
+
+    pub fn connection(&mut self, from: usize, to: usize) -> usize {
+        if let Some(innovation) = self.connections.get(&(from, to)) {
+            return *innovation;
+        }
+        let innovation = self.next_innovation;
+        self.next_innovation += 1;
+        self.connections.insert((from, to), innovation);
+        innovation
+    }
+
+    /// Node id for splitting the given connection. Genomes that already own that node get a fresh one.
+    pub fn split(&mut self, innovation: usize, taken: impl Fn(usize) -> bool) -> usize {
+        match self.splits.get(&innovation) {
+            Some(id) if !taken(*id) => *id,
+            Some(_) => self.new_node(),
+            None => {
+                let id = self.new_node();
+                self.splits.insert(innovation, id);
+                id
+            }
+        }
+    }
+
+    /// The tracker as tab separated `tracker`, `fixed`, `innovation` and `split` lines for world snapshots.
+    /// Entries are sorted so the same state always gives the same text.
+    pub fn encode(&self) -> String {
+        let mut lines = vec![format!("tracker\t{}\t{}", self.next_node, self.next_innovation)];
+        let mut fixed: Vec<(String, usize)> = self.fixed_nodes.iter().map(|(key, id)| (key.encode(), *id)).collect();
+        fixed.sort_by_key(|(_, id)| *id);
+        lines.extend(fixed.iter().map(|(key, id)| format!("fixed\t{}\t{}", key, id)));
+        let mut connections: Vec<(&(usize, usize), &usize)> = self.connections.iter().collect();
+        connections.sort_by_key(|(_, innovation)| **innovation);
+        lines.extend(connections.iter().map(|((from, to), innovation)| format!("innovation\t{}\t{}\t{}", from, to, innovation)));
+        let mut splits: Vec<(&usize, &usize)> = self.splits.iter().collect();
+        splits.sort();
+        lines.extend(splits.iter().map(|(innovation, id)| format!("split\t{}\t{}", innovation, id)));
+        lines.join("\n") + "\n"
+    }
+
+    /// Applies one line written by `encode`, split at its tabs. Returns None if the line is malformed.
+    pub fn decode_line(&mut self, fields: &[&str]) -> Option<()> {
+        match fields {
+            ["tracker", next_node, next_innovation] => {
+                self.next_node = next_node.parse().ok()?;
+                self.next_innovation = next_innovation.parse().ok()?;
+            }
+            ["fixed", key, id] => {
+                self.fixed_nodes.insert(NodeKey::decode(key)?, id.parse().ok()?);
+            }
+            ["innovation", from, to, innovation] => {
+                self.connections.insert((from.parse().ok()?, to.parse().ok()?), innovation.parse().ok()?);
+            }
+            ["split", innovation, id] => {
+                self.splits.insert(innovation.parse().ok()?, id.parse().ok()?);
+            }
+            _ => return None,
+        }
+        Some(())
+    }
+
+    fn new_node(&mut self) -> usize {
+        let id = self.next_node;
+        self.next_node += 1;
+        id
+    }
+}
@@ -0,0 +1,40 @@
+use macroquad::prelude::*;
+use crate::utils::{CELL_SIZE, MENU_WIDTH};
+
+const STAT_LINES: f32 = 5.0;
+pub const LINE_HEIGHT: f32 = 25.0;
+pub const BUTTON_HEIGHT: f32 = 30.0;
+
+/// Screen areas of the world view and the menu column, recomputed from the window size every frame.
+#[derive(Debug, Clone, Copy)]
+pub struct Layout {
+    pub world: Rect, // the world view, always anchored at the top left corner
+    pub menu: Rect, // the whole menu column
+    pub gallery: Rect,
+    pub stats: Rect, // the lines of sim statistics
+    pub controls: Rect, // graphics toggle, playback buttons and speed readout
+    pub lower: Rect, // tabbed brain, charts and settings panel filling the rest of the column
+}
+
+impl Layout {
+    pub fn current() -> Layout {
+        Layout::for_size(screen_width(), screen_height())
+    }
+
+    /// The menu keeps its preferred width unless the window is too narrow, and the world view takes the rest.
+    pub fn for_size(width: f32, height: f32) -> Layout {
+        let menu_width = MENU_WIDTH.min(width * 0.4);
+        let world = Rect::new(0.0, 0.0, width - menu_width, height);
+        let menu = Rect::new(world.w, 0.0, menu_width, height);
+
+        let inner_x = menu.x + CELL_SIZE;
+        let inner_width = menu_width - 2.0 * CELL_SIZE;
+        let gallery = Rect::new(inner_x, CELL_SIZE, inner_width, inner_width.min(height * 0.32));
+        let stats = Rect::new(inner_x + CELL_SIZE, gallery.bottom() + CELL_SIZE, inner_width - 2.0 * CELL_SIZE, STAT_LINES * LINE_HEIGHT);
+        let controls = Rect::new(stats.x, stats.bottom() + 5.0, stats.w, 2.0 * BUTTON_HEIGHT + 5.0 + LINE_HEIGHT);
+        let lower_y = controls.bottom() + CELL_SIZE;
+        let lower = Rect::new(menu.x, lower_y, menu_width, (height - lower_y).max(0.0));
+
+        Layout { world, menu, gallery, stats, controls, lower }
+    }
+}
@@ -0,0 +1,86 @@
+pub mod cell;
+pub mod grid;
+pub mod organism;
+pub mod utils;
+pub mod organism_manager;
+pub mod overlay;
+pub mod params;
+pub mod raster;
+pub mod brain;
+pub mod brain_view;
+pub mod camera;
+pub mod capture;
+pub mod charts;
+pub mod commands;
+pub mod controls;
+pub mod designer;
+pub mod gallery;
+pub mod editor;
+pub mod layout;
+pub mod library;
+pub mod innovation;
+pub mod mutation;
+pub mod network;
+pub mod senses;
+pub mod snapshot;
+pub mod tui;
+pub mod ui;
+pub mod tuner;
+
+use ::rand::{Rng, rngs::StdRng};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+    None
+}
+impl Direction {
+    pub fn random_direction(rng: &mut StdRng) -> Direction {
+        match (*rng).gen_range(0..=3) {
+            0 => Direction::Up,
+            1 => Direction::Down,
+            2 => Direction::Left,
+            3 => Direction::Right,
+            _ => Direction::None,
+        }
+    }
+    pub fn x_offset(&self) -> f32 {
+        match self {
+            Direction::Up => 0.0,
+            Direction::Down => 0.0,
+            Direction::Left => -1.0,
+            Direction::Right => 1.0,
+            Direction::None => 0.0,
+        }
+    }
+    pub fn y_offset(&self) -> f32 {
+        match self {
+            Direction::Up => -1.0,
+            Direction::Down => 1.0,
+            Direction::Left => 0.0,
+            Direction::Right => 0.0,
+            Direction::None => 0.0,
+        }
+    }
+    pub fn rotated(&self, clockwise: bool) -> Direction {
+        match (self, clockwise) {
+            (Direction::Up, true) | (Direction::Down, false) => Direction::Right,
+            (Direction::Right, true) | (Direction::Left, false) => Direction::Down,
+            (Direction::Down, true) | (Direction::Up, false) => Direction::Left,
+            (Direction::Left, true) | (Direction::Right, false) => Direction::Up,
+            (Direction::None, _) => Direction::None,
+        }
+    }
+    /// Number of clockwise quarter turns from `Up`.
+    pub fn quarter_turns(&self) -> usize {
+        match self {
+            Direction::Up | Direction::None => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
+    }
+}
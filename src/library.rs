@@ -0,0 +1,77 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::cell::Cell;
+use crate::organism::Organism;
+
+/// A named anatomy that can be placed into the world.
+#[derive(Debug, Clone)]
+pub struct Genome {
+    pub name: String,
+    pub cells: Vec<(i32, i32, Cell)>,
+}
+
+/// Anatomies available to the editor. Starts with the organism the world is seeded with.
+pub struct GenomeLibrary {
+    pub genomes: Vec<Genome>,
+}
+
+impl Default for GenomeLibrary {
+    fn default() -> Self {
+        GenomeLibrary::new()
+    }
+}
+
+impl GenomeLibrary {
+    pub fn new() -> Self {
+        GenomeLibrary {
+            genomes: vec![Genome { name: "Starter".to_owned(), cells: GenomeLibrary::starter() }],
+        }
+    }
+
+    pub fn starter() -> Vec<(i32, i32, Cell)> {
+        vec![(-1, -1, Cell::Mover), (0, 0, Cell::Mouth), (1, 1, Cell::Producer)]
+    }
+
+    /// Adds an anatomy unless an identical one is already stored. Returns its index.
+    pub fn add(&mut self, name: String, cells: Vec<(i32, i32, Cell)>) -> usize {
+        if let Some(index) = self.genomes.iter().position(|genome| genome.cells == cells) {
+            return index;
+        }
+        self.genomes.push(Genome { name, cells });
+        self.genomes.len() - 1
+    }
+
+    /// Reads genomes stored as one `name<TAB>anatomy` line each and adds them to the library.
+    pub fn load(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        for line in fs::read_to_string(path)?.lines() {
+            if let Some((name, encoded)) = line.split_once('\t') {
+                let cells = Organism::decode_anatomy(encoded);
+                if !cells.is_empty() {
+                    self.add(name.to_owned(), cells);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let lines: Vec<String> = self.genomes
+            .iter()
+            .map(|genome| format!("{}\t{}", genome.name, Organism::encode_cells(&genome.cells)))
+            .collect();
+        fs::write(path, lines.join("\n") + "\n")
+    }
+
+    pub fn get(&self, index: usize) -> Option<&Genome> {
+        self.genomes.get(index)
+    }
+
+    pub fn len(&self) -> usize {
+        self.genomes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.genomes.is_empty()
+    }
+}
@@ -1,58 +1,84 @@
-mod cell;
-mod grid;
-mod organism;
-mod utils;
-mod organism_manager;
-mod brain;
-#[cfg(feature = "tuning")]
-mod tuner;
-
-use ::rand::{Rng, rngs::StdRng};
-use grid::Grid;
-use organism_manager::OrganismManager;
+use std::io;
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use organisms::capture::CaptureOptions;
+use organisms::commands;
+use organisms::grid::Grid;
+use organisms::organism_manager::OrganismManager;
+use organisms::params::{config_line, SETTINGS};
+use organisms::controls::SimControls;
+use organisms::layout::Layout;
+use organisms::utils::{GENOME_LIBRARY_FILE, CAPTURE_INTERVAL, CAPTURE_SCALE};
 use macroquad::prelude::*;
 
-#[cfg(feature = "tuning")]
-const TUNING: bool = true;
-#[cfg(not(feature = "tuning"))]
-const TUNING: bool = false;
+/// Artificial life simulation. Opens the window when no command is given.
+#[derive(Parser)]
+#[command(name = "organisms")]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Settings file to start from, as saved from the settings panel or by `tune`
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Override one setting, as a `key=value` line of a config file (repeatable), e.g. --set plasticity=2
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE", value_parser = parse_setting)]
+    settings: Vec<String>,
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-    None
+    /// Seed for every random choice in the world
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+
+    /// Where the command writes its result (see each command)
+    #[arg(long, short, global = true)]
+    output: Option<PathBuf>,
 }
-impl Direction {
-    pub fn random_direction(rng: &mut StdRng) -> Direction {
-        match (*rng).gen_range(0..=3) {
-            0 => Direction::Up,
-            1 => Direction::Down,
-            2 => Direction::Left,
-            3 => Direction::Right,
-            _ => Direction::None,
-        }
-    }
-    pub fn x_offset(&self) -> f32 {
-        match self {
-            Direction::Up => 0.0,
-            Direction::Down => 0.0,
-            Direction::Left => -1.0,
-            Direction::Right => 1.0,
-            Direction::None => 0.0,
-        }
-    }
-    pub fn y_offset(&self) -> f32 {
-        match self {
-            Direction::Up => -1.0,
-            Direction::Down => 1.0,
-            Direction::Left => 0.0,
-            Direction::Right => 0.0,
-            Direction::None => 0.0,
-        }
-    }
+
+#[derive(Subcommand)]
+enum Command {
+    /// Open the simulation window. --output is where the P and R captures go
+    Run,
+    /// Draw the simulation in the terminal, for machines without a display
+    Tui,
+    /// Run without any display. --output saves a snapshot of the run
+    Headless {
+        /// Ticks to run
+        #[arg(long)]
+        ticks: u64,
+        /// Directory to record PNG frames to
+        #[arg(long)]
+        record: Option<PathBuf>,
+        /// Ticks between recorded frames
+        #[arg(long, default_value_t = CAPTURE_INTERVAL as u64)]
+        every: u64,
+        /// Pixels per tile in recorded frames
+        #[arg(long, default_value_t = CAPTURE_SCALE)]
+        scale: usize,
+        /// Also assemble the recorded frames into timelapse.gif
+        #[arg(long)]
+        gif: bool,
+    },
+    /// Search for settings that keep the population going. --output is the config written (tuned.cfg)
+    Tune,
+    /// Load the world of a snapshot and keep watching it, paused, in the window or the terminal
+    Replay {
+        file: PathBuf,
+        /// Use the terminal instead of the window
+        #[arg(long)]
+        tui: bool,
+    },
+    /// Add the most populous species of a snapshot to the genome library (--output, genomes.txt by default)
+    ExportGenome {
+        snapshot: PathBuf,
+        /// How many species to export
+        #[arg(long, default_value_t = 1)]
+        top: usize,
+    },
+    /// Print the statistics of a snapshot's world, or write them to --output
+    Stats {
+        snapshot: PathBuf,
+    },
 }
 
 fn window_conf() -> Conf {
@@ -61,26 +87,74 @@ fn window_conf() -> Conf {
         window_title: "Life Engine".to_owned(),
         window_width: screen_size.0, // Set your desired width here
         window_height: screen_size.1, // Set your desired height here
+        window_resizable: true,
         ..Default::default()
     }
 }
 
-#[macroquad::main(window_conf)]
-async fn main() {
-    if TUNING {
-        #[cfg(feature = "tuning")]
-        tuner::main();
-        return;
+/// Accepts `key=value` for a known setting with a numeric value.
+fn parse_setting(text: &str) -> Result<String, String> {
+    let Some((key, value)) = config_line(text) else {
+        return Err("expected KEY=VALUE".to_owned());
+    };
+    if !SETTINGS.iter().any(|setting| setting.key == key) {
+        let keys: Vec<&str> = SETTINGS.iter().map(|setting| setting.key).collect();
+        return Err(format!("unknown setting '{}', expected one of: {}", key, keys.join(", ")));
     }
+    value.parse::<f32>().map_err(|_| format!("'{}' is not a number", value))?;
+    Ok(text.to_owned())
+}
+
+fn main() {
+    if let Err(error) = run(Cli::parse()) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
 
-    let mut organism_manager = OrganismManager::new();
+fn run(cli: Cli) -> io::Result<()> {
+    let (config, settings, seed, output) = (cli.config.as_deref(), cli.settings.as_slice(), cli.seed, cli.output.clone());
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => open_window(commands::new_world(config, settings, seed)?, output, false),
+        Command::Tui => organisms::tui::run(&mut commands::new_world(config, settings, seed)?, false),
+        Command::Headless { ticks, record, every, scale, gif } => {
+            let record = record.map(|directory| CaptureOptions { directory, interval: every as u128, scale, gif, ..CaptureOptions::default() });
+            commands::headless(config, settings, seed, ticks as u128, record, output.as_deref())
+        }
+        Command::Tune => commands::tune(config, settings, seed, output.as_deref()),
+        Command::Replay { file, tui } => {
+            let mut organism_manager = commands::load_snapshot(&file, config, settings, seed)?;
+            if tui {
+                organisms::tui::run(&mut organism_manager, true)
+            } else {
+                open_window(organism_manager, output, true)
+            }
+        }
+        Command::ExportGenome { snapshot, top } => commands::export_genome(&commands::load_snapshot(&snapshot, config, settings, seed)?, top, output.as_deref()),
+        Command::Stats { snapshot } => commands::stats(&commands::load_snapshot(&snapshot, config, settings, seed)?, output.as_deref()),
+    }
+}
+
+fn open_window(mut organism_manager: OrganismManager, capture_directory: Option<PathBuf>, paused: bool) -> io::Result<()> {
+    let _ = organism_manager.library.load(GENOME_LIBRARY_FILE); // the file only exists once a design has been saved
+    if let Some(directory) = capture_directory {
+        organism_manager.capture.directory = directory;
+    }
+    macroquad::Window::from_config(window_conf(), run_window(organism_manager, paused));
+    Ok(())
+}
 
-    organism_manager.init();
+async fn run_window(mut organism_manager: OrganismManager, paused: bool) {
+    let mut controls = SimControls::new();
+    controls.paused = paused;
 
     loop {
         clear_background(BLACK);
 
-        organism_manager.update();
+        controls.handle_keys();
+        controls.run_frame(organism_manager.grid.graphics_on(), || organism_manager.tick());
+        organism_manager.draw();
+        controls.draw(&Layout::current());
 
         next_frame().await;
     }
@@ -0,0 +1,267 @@
+use rand::{Rng, rngs::StdRng};
+use crate::brain::PlasticityMode;
+use crate::utils::{MUTATION_RATE, CHANGE_CELL_WEIGHT, REMOVE_CELL_WEIGHT, ADD_CELL_WEIGHT, SWAP_CELL_WEIGHT, DUPLICATE_PART_WEIGHT,
+    WEIGHT_MUTATION_PROB, WEIGHT_MUTATION_STD, MUTATION_RATE_ADAPTATION, MIN_MUTATION_RATE, MAX_MUTATION_RATE,
+    ADD_CONNECTION_PROB, ADD_NODE_PROB, DISABLE_CONNECTION_PROB, ACTIVATION_MUTATION_PROB, PLASTICITY};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MutationOp {
+    Change,
+    Remove,
+    Add,
+    Swap,
+    Duplicate,
+}
+
+/// Mutation rates carried by each genome. They are inherited and mutated themselves, so lineages can evolve their evolvability.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MutationRates {
+    pub anatomy: f32, // mean of the Poisson distributed number of anatomy mutations per birth
+    pub weight_prob: f32, // chance for each brain weight to be perturbed
+    pub weight_std: f32, // std dev of the gaussian step applied to a perturbed weight
+}
+
+impl Default for MutationRates {
+    fn default() -> Self {
+        MutationRates {
+            anatomy: MUTATION_RATE,
+            weight_prob: WEIGHT_MUTATION_PROB,
+            weight_std: WEIGHT_MUTATION_STD,
+        }
+    }
+}
+
+impl MutationRates {
+    /// Log-normal self-adaptation: each rate is scaled by `exp(tau * N(0, 1))` and clamped to the policy bounds.
+    pub fn mutated(&self, policy: &MutationPolicy, rng: &mut StdRng) -> MutationRates {
+        let mut adapt = |rate: f32, max: f32| {
+            (rate * (policy.rate_adaptation * gaussian(rng)).exp()).clamp(policy.min_rate, max)
+        };
+        MutationRates {
+            anatomy: adapt(self.anatomy, policy.max_rate),
+            weight_prob: adapt(self.weight_prob, 1.0),
+            weight_std: adapt(self.weight_std, policy.max_rate),
+        }
+    }
+
+    /// These rates scaled by how much the reference rates changed from `old` to `new`, so a lineage keeps its evolved
+    /// difference to the reference. A reference rate raised from zero has nothing to scale and is copied as is.
+    pub fn rescaled(&self, old: &MutationRates, new: &MutationRates) -> MutationRates {
+        let rescale = |rate: f32, old: f32, new: f32| if old == 0.0 { new } else { rate * new / old };
+        MutationRates {
+            anatomy: rescale(self.anatomy, old.anatomy, new.anatomy),
+            weight_prob: rescale(self.weight_prob, old.weight_prob, new.weight_prob).min(1.0),
+            weight_std: rescale(self.weight_std, old.weight_std, new.weight_std),
+        }
+    }
+
+    pub fn mutation_count(&self, rng: &mut StdRng) -> usize {
+        poisson(self.anatomy, rng)
+    }
+
+    pub fn mutate_weight(&self, weight: &mut f32, rng: &mut StdRng) {
+        if rng.gen::<f32>() < self.weight_prob {
+            *weight += gaussian(rng) * self.weight_std;
+        }
+    }
+}
+
+/// Describes how genomes are mutated at birth. Anatomy operator weights are relative and do not need to sum to 1.
+#[derive(Debug, Clone)]
+pub struct MutationPolicy {
+    pub change_weight: f32,
+    pub remove_weight: f32,
+    pub add_weight: f32,
+    pub swap_weight: f32,
+    pub duplicate_weight: f32,
+    pub add_connection_prob: f32, // brain topology mutations, each rolled once per birth
+    pub add_node_prob: f32,
+    pub disable_connection_prob: f32,
+    pub activation_prob: f32,
+    pub plasticity: PlasticityMode, // whether brains learn during life and pass learned weights on
+    pub rate_adaptation: f32, // learning rate (tau) of the heritable mutation rates, 0 disables self-adaptation
+    pub min_rate: f32,
+    pub max_rate: f32,
+}
+
+impl Default for MutationPolicy {
+    fn default() -> Self {
+        MutationPolicy {
+            change_weight: CHANGE_CELL_WEIGHT,
+            remove_weight: REMOVE_CELL_WEIGHT,
+            add_weight: ADD_CELL_WEIGHT,
+            swap_weight: SWAP_CELL_WEIGHT,
+            duplicate_weight: DUPLICATE_PART_WEIGHT,
+            add_connection_prob: ADD_CONNECTION_PROB,
+            add_node_prob: ADD_NODE_PROB,
+            disable_connection_prob: DISABLE_CONNECTION_PROB,
+            activation_prob: ACTIVATION_MUTATION_PROB,
+            plasticity: PLASTICITY,
+            rate_adaptation: MUTATION_RATE_ADAPTATION,
+            min_rate: MIN_MUTATION_RATE,
+            max_rate: MAX_MUTATION_RATE,
+        }
+    }
+}
+
+impl MutationPolicy {
+    pub fn choose_op(&self, rng: &mut StdRng) -> MutationOp {
+        let ops = [
+            (MutationOp::Change, self.change_weight),
+            (MutationOp::Remove, self.remove_weight),
+            (MutationOp::Add, self.add_weight),
+            (MutationOp::Swap, self.swap_weight),
+            (MutationOp::Duplicate, self.duplicate_weight),
+        ];
+        let total: f32 = ops.iter().map(|(_, w)| w.max(0.0)).sum();
+        if total <= 0.0 {
+            return MutationOp::Change;
+        }
+
+        let mut pick = rng.gen::<f32>() * total;
+        for (op, weight) in ops.iter() {
+            pick -= weight.max(0.0);
+            if pick < 0.0 {
+                return *op;
+            }
+        }
+        MutationOp::Change
+    }
+}
+
+/// Knuth's method, fine for the small means used for mutation counts.
+pub fn poisson(lambda: f32, rng: &mut StdRng) -> usize {
+    if lambda <= 0.0 {
+        return 0;
+    }
+
+    let limit = (-lambda).exp();
+    let mut count = 0;
+    let mut product = rng.gen::<f32>();
+    while product > limit {
+        count += 1;
+        product *= rng.gen::<f32>();
+    }
+    count
+}
+
+/// Standard normal sample via the Box-Muller transform.
+pub fn gaussian(rng: &mut StdRng) -> f32 {
+    let u1 = rng.gen::<f32>().max(f32::MIN_POSITIVE);
+    let u2 = rng.gen::<f32>();
+    (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+
+    const SAMPLES: usize = 20000;
+
+    #[test]
+    fn poisson_counts_average_to_the_mean() {
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(poisson(0.0, &mut rng), 0);
+        assert_eq!(poisson(-1.0, &mut rng), 0);
+        for lambda in [0.5, 1.0, 3.0] {
+            let total: usize = (0..SAMPLES).map(|_| poisson(lambda, &mut rng)).sum();
+            let mean = total as f32 / SAMPLES as f32;
+            assert!((mean - lambda).abs() < 0.05 * lambda.max(1.0), "mean {} for lambda {}", mean, lambda);
+        }
+    }
+
+    #[test]
+    fn gaussian_samples_are_standard_normal() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let samples: Vec<f32> = (0..SAMPLES).map(|_| gaussian(&mut rng)).collect();
+        let mean = samples.iter().sum::<f32>() / SAMPLES as f32;
+        let variance = samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / SAMPLES as f32;
+        assert!(samples.iter().all(|x| x.is_finite()));
+        assert!(mean.abs() < 0.03, "mean {}", mean);
+        assert!((variance - 1.0).abs() < 0.05, "variance {}", variance);
+    }
+
+    #[test]
+    fn operators_are_picked_in_proportion_to_their_weights() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let policy = MutationPolicy { change_weight: 1.0, remove_weight: 0.0, add_weight: 3.0, swap_weight: 0.0, duplicate_weight: -2.0, ..MutationPolicy::default() };
+        let mut counts = [0usize; 5];
+        for _ in 0..SAMPLES {
+            counts[policy.choose_op(&mut rng) as usize] += 1;
+        }
+        assert_eq!(counts[MutationOp::Remove as usize] + counts[MutationOp::Swap as usize] + counts[MutationOp::Duplicate as usize], 0);
+        let add_share = counts[MutationOp::Add as usize] as f32 / SAMPLES as f32;
+        assert!((add_share - 0.75).abs() < 0.02, "add share {}", add_share);
+
+        let none = MutationPolicy { change_weight: 0.0, remove_weight: 0.0, add_weight: 0.0, swap_weight: 0.0, duplicate_weight: 0.0, ..MutationPolicy::default() };
+        assert_eq!(none.choose_op(&mut rng), MutationOp::Change);
+    }
+
+    #[test]
+    fn weights_are_perturbed_with_the_genome_chance() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let never = MutationRates { weight_prob: 0.0, ..MutationRates::default() };
+        let always = MutationRates { weight_prob: 1.0, ..MutationRates::default() };
+        let mut weight = 0.5;
+        for _ in 0..100 {
+            never.mutate_weight(&mut weight, &mut rng);
+        }
+        assert_eq!(weight, 0.5);
+        let changed = (0..100).filter(|_| {
+            let before = weight;
+            always.mutate_weight(&mut weight, &mut rng);
+            weight != before
+        }).count();
+        assert_eq!(changed, 100);
+    }
+
+    #[test]
+    fn mutated_rates_stay_within_the_policy_bounds() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let policy = MutationPolicy { rate_adaptation: 2.0, min_rate: 0.01, max_rate: 4.0, ..MutationPolicy::default() };
+        let mut rates = MutationRates::default();
+        let mut seen = Vec::new();
+        for _ in 0..1000 {
+            rates = rates.mutated(&policy, &mut rng);
+            assert!((0.01..=4.0).contains(&rates.anatomy) && (0.01..=4.0).contains(&rates.weight_std));
+            assert!((0.01..=1.0).contains(&rates.weight_prob));
+            seen.push(rates.anatomy);
+        }
+        assert!(seen.contains(&0.01) && seen.contains(&4.0), "a strong adaptation should reach both bounds");
+    }
+
+    #[test]
+    fn mutated_rates_are_unchanged_without_adaptation() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let policy = MutationPolicy { rate_adaptation: 0.0, ..MutationPolicy::default() };
+        let rates = MutationRates { anatomy: 1.5, weight_prob: 0.2, weight_std: 0.3 };
+        assert_eq!(rates.mutated(&policy, &mut rng), rates);
+    }
+
+    #[test]
+    fn mutated_rates_spread_log_normally_around_the_parent() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let policy = MutationPolicy { rate_adaptation: 0.2, min_rate: 0.0, max_rate: 100.0, ..MutationPolicy::default() };
+        let rates = MutationRates { anatomy: 1.0, weight_prob: 0.1, weight_std: 0.5 };
+        let logs: Vec<f32> = (0..SAMPLES).map(|_| rates.mutated(&policy, &mut rng).anatomy.ln()).collect();
+        let mean = logs.iter().sum::<f32>() / SAMPLES as f32;
+        let std = (logs.iter().map(|x| (x - mean) * (x - mean)).sum::<f32>() / SAMPLES as f32).sqrt();
+        assert!(mean.abs() < 0.01, "mean log step {}", mean);
+        assert!((std - 0.2).abs() < 0.01, "std of log step {}", std);
+    }
+
+    #[test]
+    fn rescaled_rates_follow_the_reference_change() {
+        let rates = MutationRates { anatomy: 1.2, weight_prob: 0.4, weight_std: 0.6 };
+        let old = MutationRates { anatomy: 0.6, weight_prob: 0.2, weight_std: 0.0 };
+        let new = MutationRates { anatomy: 0.3, weight_prob: 0.8, weight_std: 0.5 };
+        let rescaled = rates.rescaled(&old, &new);
+        assert!((rescaled.anatomy - 0.6).abs() < 1e-6);
+        assert_eq!(rescaled.weight_prob, 1.0, "chances are capped at certainty");
+        assert_eq!(rescaled.weight_std, 0.5, "a rate raised from zero takes the new value");
+        let unchanged = MutationRates::default();
+        let same = rates.rescaled(&unchanged, &unchanged);
+        assert!((same.anatomy - rates.anatomy).abs() < 1e-6 && (same.weight_prob - rates.weight_prob).abs() < 1e-6 && (same.weight_std - rates.weight_std).abs() < 1e-6);
+    }
+}
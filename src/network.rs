@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+use crate::brain::{Activation, ConnectionGene, NodeGene, NodeKind, OUTPUT_NUM};
+
+#[derive(Debug, Clone, Copy)]
+struct Neuron {
+    node: u32, // index into the brain's node and value arrays
+    start: u32, // range of this neuron's incoming edges in the flat edge arrays
+    end: u32,
+    activation: Activation,
+}
+
+/// Phenotype compiled from a brain genome. Incoming edges are stored contiguously per neuron
+/// (compressed sparse rows), so evaluation walks flat arrays and never allocates.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    inputs: Vec<(u32, u32)>, // (node index, sensor slot)
+    neurons: Vec<Neuron>, // every non-input node, in evaluation order
+    sources: Vec<u32>, // node index feeding each edge
+    weights: Vec<f32>, // effective weight of each edge
+    edge_connections: Vec<u32>, // genome connection behind each edge, used to write learned offsets back
+    outputs: [u32; OUTPUT_NUM],
+}
+
+impl Network {
+    pub fn compile(nodes: &[NodeGene], connections: &[ConnectionGene]) -> Network {
+        let index: HashMap<usize, u32> = nodes.iter().enumerate().map(|(i, node)| (node.id, i as u32)).collect();
+        let mut incoming: Vec<Vec<u32>> = vec![Vec::new(); nodes.len()];
+        for (i, connection) in connections.iter().enumerate() {
+            if !connection.enabled {
+                continue;
+            }
+            if let (Some(_), Some(&to)) = (index.get(&connection.from), index.get(&connection.to)) {
+                incoming[to as usize].push(i as u32);
+            }
+        }
+
+        let mut network = Network::default();
+        for (i, node) in nodes.iter().enumerate() {
+            match node.kind {
+                NodeKind::Input(_, slot) => {
+                    network.inputs.push((i as u32, slot as u32));
+                    continue;
+                }
+                NodeKind::Output(output) => network.outputs[output] = i as u32,
+                NodeKind::Hidden => {}
+            }
+
+            let start = network.sources.len() as u32;
+            for &connection in incoming[i].iter() {
+                let gene = &connections[connection as usize];
+                network.sources.push(index[&gene.from]);
+                network.weights.push(gene.weight + gene.learned);
+                network.edge_connections.push(connection);
+            }
+            network.neurons.push(Neuron { node: i as u32, start, end: network.sources.len() as u32, activation: node.activation });
+        }
+        network
+    }
+
+    /// Writes the new activation of every node into `values`. Sources evaluated later in the order
+    /// still hold last tick's value when read, which gives recurrent connections their memory.
+    pub fn evaluate(&self, inputs: &[f32], values: &mut [f32]) {
+        for &(node, slot) in self.inputs.iter() {
+            values[node as usize] = inputs.get(slot as usize).copied().unwrap_or(0.0);
+        }
+
+        for neuron in self.neurons.iter() {
+            let (start, end) = (neuron.start as usize, neuron.end as usize);
+            let sum: f32 = self.weights[start..end]
+                .iter()
+                .zip(self.sources[start..end].iter())
+                .map(|(weight, source)| weight * values[*source as usize])
+                .sum();
+            values[neuron.node as usize] = neuron.activation.apply(sum);
+        }
+    }
+
+    pub fn output(&self, values: &[f32], output: usize) -> f32 {
+        values[self.outputs[output] as usize]
+    }
+
+    /// Applies `rate * pre * post` to every edge, keeping the genome's learned offsets in sync with the flat weights.
+    pub fn learn(&mut self, connections: &mut [ConnectionGene], values: &[f32], rate: f32, max_learned: f32) {
+        for neuron in self.neurons.iter() {
+            let post = values[neuron.node as usize];
+            for edge in neuron.start as usize..neuron.end as usize {
+                let pre = values[self.sources[edge] as usize];
+                let connection = &mut connections[self.edge_connections[edge] as usize];
+                connection.learned = (connection.learned + rate * pre * post).clamp(-max_learned, max_learned);
+                self.weights[edge] = connection.weight + connection.learned;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use super::*;
+    use crate::brain::Brain;
+    use crate::innovation::InnovationTracker;
+    use crate::mutation::{MutationPolicy, MutationRates};
+    use crate::senses::input_keys;
+
+    /// Straight evaluation of the genome: every node in order sums its enabled incoming connections.
+    fn naive_evaluate(brain: &Brain, inputs: &[f32], values: &mut [f32]) {
+        let index: HashMap<usize, usize> = brain.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+        for (i, node) in brain.nodes.iter().enumerate() {
+            values[i] = match node.kind {
+                NodeKind::Input(_, slot) => inputs.get(slot).copied().unwrap_or(0.0),
+                _ => {
+                    let sum: f32 = brain.connections
+                        .iter()
+                        .filter(|connection| connection.enabled && connection.to == node.id)
+                        .map(|connection| (connection.weight + connection.learned) * values[index[&connection.from]])
+                        .sum();
+                    node.activation.apply(sum)
+                }
+            };
+        }
+    }
+
+    fn assert_matches_genome(brain: &Brain, rng: &mut StdRng) {
+        let network = Network::compile(&brain.nodes, &brain.connections);
+        let input_num = brain.nodes.iter().filter(|node| matches!(node.kind, NodeKind::Input(..))).count();
+        let mut compiled = vec![0.0; brain.nodes.len()];
+        let mut naive = compiled.clone();
+        for tick in 0..10 {
+            let inputs: Vec<f32> = (0..input_num).map(|_| rng.gen_range(0.0..1.0)).collect();
+            network.evaluate(&inputs, &mut compiled);
+            naive_evaluate(brain, &inputs, &mut naive);
+            for (i, (a, b)) in compiled.iter().zip(naive.iter()).enumerate() {
+                assert!((a - b).abs() < 1e-5, "node {} differs on tick {}: {} vs {}", brain.nodes[i].id, tick, a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn compiled_network_matches_the_genome_of_a_new_brain() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let brain = Brain::new(&input_keys(&[(0, -1), (1, 0)]), &mut InnovationTracker::new(), &mut rng);
+        assert_matches_genome(&brain, &mut rng);
+    }
+
+    #[test]
+    fn compiled_network_matches_the_genome_of_mutated_brains() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let mut tracker = InnovationTracker::new();
+        let policy = MutationPolicy { add_connection_prob: 1.0, add_node_prob: 1.0, disable_connection_prob: 0.5, activation_prob: 0.5, ..MutationPolicy::default() };
+        let keys = input_keys(&[(0, -1), (1, 0)]);
+        let mut brain = Brain::new(&keys, &mut tracker, &mut rng);
+        for generation in 0..30 {
+            // gaining and losing an eye exercises input adaptation along the way
+            let keys = if generation % 3 == 0 { input_keys(&[(0, -1)]) } else { keys.clone() };
+            brain = brain.child_brain(&keys, 4, &MutationRates::default(), &policy, &mut tracker, &mut rng);
+            for connection in brain.connections.iter_mut() {
+                connection.learned = rng.gen_range(-0.5..0.5);
+            }
+            assert_matches_genome(&brain, &mut rng);
+        }
+        assert!(brain.hidden_num() > 0);
+        // recurrent edges read a node evaluated at or after their target
+        let order: HashMap<usize, f32> = brain.nodes.iter().map(|node| (node.id, node.order)).collect();
+        assert!(brain.connections.iter().any(|connection| connection.enabled && order[&connection.from] >= order[&connection.to]));
+    }
+
+    #[test]
+    fn learning_updates_the_genome_and_the_compiled_weights_together() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut brain = Brain::new(&input_keys(&[(0, -1)]), &mut InnovationTracker::new(), &mut rng);
+        let mut network = Network::compile(&brain.nodes, &brain.connections);
+        let inputs: Vec<f32> = (0..brain.nodes.len()).map(|_| rng.gen_range(0.0..1.0)).collect();
+        let mut values = vec![0.0; brain.nodes.len()];
+        network.evaluate(&inputs, &mut values);
+
+        let index: HashMap<usize, usize> = brain.nodes.iter().enumerate().map(|(i, node)| (node.id, i)).collect();
+        network.learn(&mut brain.connections, &values, 0.5, 0.1);
+        for connection in brain.connections.iter() {
+            let expected = (0.5 * values[index[&connection.from]] * values[index[&connection.to]]).clamp(-0.1, 0.1);
+            assert!((connection.learned - expected).abs() < 1e-6);
+        }
+
+        // the learned offsets reach the flat weights without recompiling
+        let mut learned = values.clone();
+        let mut naive = values.clone();
+        network.evaluate(&inputs, &mut learned);
+        naive_evaluate(&brain, &inputs, &mut naive);
+        for (a, b) in learned.iter().zip(naive.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+}
@@ -1,7 +1,11 @@
-use crate::{brain::Brain, cell::Cell, grid, utils::FOOD_BENEFIT, Direction};
+use crate::{brain::{Action, Brain, PlasticityMode}, cell::Cell, senses, grid, Direction};
 use std::collections::VecDeque;
 use rand::{rngs::StdRng, Rng, seq::SliceRandom};
-use crate::utils::{WIDTH, HEIGHT, LIFETIME_MULTIPLIER, MUTATION_RATE, HUNGER_RATE, REPRODUCTION_ENEGRGY_MULTIPLER};
+use crate::mutation::{MutationOp, MutationPolicy, MutationRates};
+use crate::innovation::InnovationTracker;
+use crate::senses::InputKey;
+use crate::params::SimParams;
+use crate::utils::{WIDTH, HEIGHT};
 
 pub struct Organism {
     pub x: usize,
@@ -13,9 +17,19 @@ pub struct Organism {
     pub lifetime: i32,
     pub satiety: f32,
     pub killed: bool,
+    pub mutation_rates: MutationRates,
+    pub heading: Direction,
+    pub species: u64, // hash of the genome frame anatomy
+    pub attacking: bool,
+    pub birth_blocked: bool, // a child found no room since the last birth, so retries are not counted again
+    pub age: u32,
+    pub parent: Option<usize>,
+    pub lineage: usize, // id of the seeded or placed organism this one descends from
+    pub offspring: usize,
+    pub reward: f32, // food eaten minus damage taken since the brain last learned
 
     cells_len: usize,
-    eye_data: Vec<f32>,
+    sensor_data: Vec<f32>,
     min_x: usize,
     max_x: usize,
     min_y: usize,
@@ -23,40 +37,38 @@ pub struct Organism {
 }
 
 impl Organism {
-    pub fn new(x: usize, y: usize, cells: Vec<(i32, i32, Cell)>, id: usize, rng: &mut StdRng) -> Self {
+    pub fn new(x: usize, y: usize, cells: Vec<(i32, i32, Cell)>, id: usize, params: &SimParams, tracker: &mut InnovationTracker, rng: &mut StdRng) -> Self {
+        let lifetime = Organism::lifetime_len(&cells, params);
+        let cells_len = cells.len();
+
+        let mut organism = Organism { x, y, cells, brain: None, id, energy: 0, lifetime, satiety: 1.0, killed: false,
+            mutation_rates: params.seed_mutation_rates(), heading: Direction::Up, attacking: true, birth_blocked: false, age: 0, parent: None, lineage: id, offspring: 0, reward: 0.0, cells_len, sensor_data: Vec::new(), species: 0, min_x: 0, max_x: 0, min_y: 0, max_y: 0};
+        organism.update_bounds();
+        organism.species = organism.species_hash();
+        if Organism::brain_quality(&organism.cells).is_some() {
+            organism.brain = Some(Brain::new(&organism.input_keys(), tracker, rng));
+        }
+        organism
+    }
+    fn lifetime_len(cells: &[(i32, i32, Cell)], params: &SimParams) -> i32 {cells.len() as i32 * params.lifetime_multiplier}
+    fn update_bounds(&mut self) {
         let mut min_x = 0;
         let mut max_x = 0;
         let mut min_y = 0;
         let mut max_y = 0;
-        for (dx, dy, _) in cells.iter() {
-            if *dx < min_x {
-                min_x = *dx;
-            }
-            if *dx > max_x {
-                max_x = *dx;
-            }
-            if *dy < min_y {
-                min_y = *dy;
-            }
-            if *dy > max_y {
-                max_y = *dy;
-            }
+        for (dx, dy, _) in self.cells.iter() {
+            min_x = min_x.min(*dx);
+            max_x = max_x.max(*dx);
+            min_y = min_y.min(*dy);
+            max_y = max_y.max(*dy);
         }
-
-        let brain: Option<Brain> = if let Some((eye_num, brain_num)) = Organism::brain_quality(&cells) {
-            Some(Brain::new(eye_num, brain_num, rng))
-        } else {
-            None
-        };
-
-        let lifetime = Organism::lifetime_len(&cells);
-        let cells_len = cells.len();
-
-        Organism { x, y, cells, brain, id, energy: 0, lifetime, satiety: 1.0, killed: false,
-            cells_len, eye_data: Vec::new(), min_x: min_x.abs() as usize, max_x: max_x as usize, min_y: min_y.abs() as usize, max_y: max_y as usize}
+        self.min_x = min_x.unsigned_abs() as usize;
+        self.max_x = max_x as usize;
+        self.min_y = min_y.unsigned_abs() as usize;
+        self.max_y = max_y as usize;
     }
-    fn lifetime_len(cells: &Vec<(i32, i32, Cell)>) -> i32 {return cells.len() as i32 * LIFETIME_MULTIPLIER;}
-    fn brain_quality(cells: &Vec<(i32, i32, Cell)>) -> Option<(usize, usize)> {
+    /// Eye and brain cell counts if the anatomy can carry a brain, which needs at least one eye and a mover.
+    pub fn brain_quality(cells: &[(i32, i32, Cell)]) -> Option<(usize, usize)> {
         let mut has_mover = false;
         let mut eye_num = 0;
         let mut brain_num = 0;
@@ -75,13 +87,19 @@ impl Organism {
             None
         }
     }
-    pub fn body_range(&self) -> (usize, usize) {(self.max_x + self.min_x + 1, self.max_y - self.min_y + 1)}
+    pub fn body_range(&self) -> (usize, usize) {(self.max_x + self.min_x + 1, self.max_y + self.min_y + 1)}
+    /// Distance from the leftmost and topmost cell to the anchor.
+    pub fn body_offset(&self) -> (usize, usize) {(self.min_x, self.min_y)}
     pub fn is_connected(&self) -> bool {
-        if self.cells.is_empty() {
+        Organism::cells_connected(&self.cells)
+    }
+    /// Whether every cell touches the rest of the body, counting diagonal neighbours.
+    pub fn cells_connected(cells: &[(i32, i32, Cell)]) -> bool {
+        if cells.is_empty() {
             return false;
         }
 
-        let mut visited = vec![false; self.cells.len()];
+        let mut visited = vec![false; cells.len()];
         let mut queue = VecDeque::new();
 
         queue.push_back(0);
@@ -89,9 +107,9 @@ impl Organism {
         let mut visited_count = 1;
 
         while let Some(index) = queue.pop_front() {
-            let (x1, y1, _) = self.cells[index];
+            let (x1, y1, _) = cells[index];
 
-            for (i, (x2, y2, _)) in self.cells.iter().enumerate() {
+            for (i, (x2, y2, _)) in cells.iter().enumerate() {
                 if !visited[i] {
                     let dx = (x1 - x2).abs();
                     let dy = (y1 - y2).abs();
@@ -105,44 +123,85 @@ impl Organism {
             }
         }
 
-        visited_count == self.cells.len()
+        visited_count == cells.len()
     }
-    pub fn child(&self, id: usize, rng: &mut StdRng) -> Organism {
-        let lifetime = Organism::lifetime_len(&self.cells);
+    /// Copy of this organism with a mutated anatomy and mutation rates, but no brain yet. Once the child has
+    /// found room in the world, `inherit_brain` gives it one.
+    pub fn child(&self, id: usize, params: &SimParams, policy: &MutationPolicy, rng: &mut StdRng) -> Organism {
+        let lifetime = Organism::lifetime_len(&self.cells, params);
         let mut child = Organism { x: self.x, y: self.y, id, cells: self.cells.clone(), brain: None, energy: 0, lifetime, satiety: 1.0, killed: false,
-            cells_len: self.cells_len, eye_data: Vec::new(), min_x: self.min_x, max_x: self.max_x, min_y: self.min_y, max_y: self.max_y};
+            mutation_rates: self.mutation_rates.mutated(policy, rng), heading: self.heading, attacking: true, birth_blocked: false, age: 0, parent: Some(self.id), lineage: self.lineage, offspring: 0, reward: 0.0, cells_len: self.cells_len, sensor_data: Vec::new(), species: 0, min_x: self.min_x, max_x: self.max_x, min_y: self.min_y, max_y: self.max_y};
         
-        child.mutate(rng);
-
-        let child_brain = if let Some((eye_num, brain_num)) = Organism::brain_quality(&child.cells) {
-            if let Some(brain) = &self.brain {
-                Some(brain.child_brain(brain_num, rng))
-            } else {
-                Some(Brain::new(eye_num, brain_num, rng))
+        child.mutate(policy, rng);
+        child.lifetime = Organism::lifetime_len(&child.cells, params);
+        child.species = child.species_hash();
+        child
+    }
+    /// Gives a child made by `child` the parent's brain, adapted to its anatomy and mutated, or a fresh brain if
+    /// the parent had none.
+    pub fn inherit_brain(&mut self, parent: &Organism, policy: &MutationPolicy, tracker: &mut InnovationTracker, rng: &mut StdRng) {
+        self.brain = Organism::brain_quality(&self.cells).map(|(_, brain_num)| {
+            let input_keys = self.input_keys();
+            match &parent.brain {
+                Some(brain) => brain.child_brain(&input_keys, brain_num, &self.mutation_rates, policy, tracker, rng),
+                None => Brain::new(&input_keys, tracker, rng),
             }
-        } else {
-            None
-        };
+        });
+    }
+    /// Unmutated copy of this organism with a fresh life, keeping its anatomy, heading, mutation rates and brain.
+    pub fn clone_as(&self, id: usize, params: &SimParams) -> Organism {
+        let lifetime = Organism::lifetime_len(&self.cells, params);
+        Organism { x: self.x, y: self.y, id, cells: self.cells.clone(), brain: self.brain.clone(), energy: 0, lifetime, satiety: 1.0, killed: false,
+            mutation_rates: self.mutation_rates, heading: self.heading, attacking: true, birth_blocked: false, age: 0, parent: Some(self.id), lineage: self.lineage, offspring: 0, reward: 0.0, cells_len: self.cells_len, sensor_data: Vec::new(), species: self.species, min_x: self.min_x, max_x: self.max_x, min_y: self.min_y, max_y: self.max_y}
+    }
+    /// The organism's state as a tab separated `organism` line for world snapshots, followed by its brain's lines.
+    /// Cells are stored as currently rotated and the heading as clockwise quarter turns from the genome frame.
+    pub fn encode_state(&self) -> String {
+        let parent = self.parent.map_or("-".to_owned(), |parent| parent.to_string());
+        let mut text = format!("organism\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            self.id, self.x, self.y, self.energy, self.lifetime, self.satiety, self.heading.quarter_turns(), self.species,
+            self.attacking, self.birth_blocked, self.age, parent, self.lineage, self.offspring, self.reward,
+            self.mutation_rates.anatomy, self.mutation_rates.weight_prob, self.mutation_rates.weight_std, Organism::encode_cells(&self.cells));
+        if let Some(brain) = &self.brain {
+            text.push_str(&brain.encode());
+        }
+        text
+    }
 
-        child.brain =  child_brain;
-        
-        child
+    /// An organism from an `organism` line, without its brain. Returns None if the line is malformed.
+    pub fn decode_state(fields: &[&str]) -> Option<Organism> {
+        let ["organism", id, x, y, energy, lifetime, satiety, turns, species, attacking, birth_blocked, age, parent, lineage, offspring, reward,
+            anatomy_rate, weight_prob, weight_std, cells] = fields else {
+            return None;
+        };
+        let cells = Organism::decode_anatomy(cells);
+        if cells.is_empty() {
+            return None;
+        }
+        let heading = (0..turns.parse::<usize>().ok()?).fold(Direction::Up, |heading, _| heading.rotated(true));
+        let parent = if *parent == "-" { None } else { Some(parent.parse().ok()?) };
+        let mutation_rates = MutationRates { anatomy: anatomy_rate.parse().ok()?, weight_prob: weight_prob.parse().ok()?, weight_std: weight_std.parse().ok()? };
+
+        let mut organism = Organism { x: x.parse().ok()?, y: y.parse().ok()?, cells_len: cells.len(), cells, brain: None, id: id.parse().ok()?,
+            energy: energy.parse().ok()?, lifetime: lifetime.parse().ok()?, satiety: satiety.parse().ok()?, killed: false, mutation_rates, heading,
+            species: species.parse().ok()?, attacking: attacking.parse().ok()?, birth_blocked: birth_blocked.parse().ok()?, age: age.parse().ok()?, parent, lineage: lineage.parse().ok()?,
+            offspring: offspring.parse().ok()?, reward: reward.parse().ok()?, sensor_data: Vec::new(), min_x: 0, max_x: 0, min_y: 0, max_y: 0 };
+        organism.update_bounds();
+        Some(organism)
     }
-    pub fn mutate(&mut self, rng: &mut StdRng) {
-        if rng.gen::<f32>() < MUTATION_RATE {
-            let val = rng.gen::<f32>();
-            if val < 0.33 {
-                self.change_cell(rng);
-            } else if val < 0.66 && self.cells_len > 1 {
-                self.remove_cell(rng);
-                self.lifetime -= LIFETIME_MULTIPLIER;
-                self.cells_len -= 1;
-            } else {
-                self.add_cell(Cell::random_cell(rng), rng);
-                self.lifetime += LIFETIME_MULTIPLIER;
-                self.cells_len += 1;
+    pub fn mutate(&mut self, policy: &MutationPolicy, rng: &mut StdRng) {
+        for _ in 0..self.mutation_rates.mutation_count(rng) {
+            match policy.choose_op(rng) {
+                MutationOp::Change => self.change_cell(rng),
+                MutationOp::Remove => self.remove_cell(rng),
+                MutationOp::Add => self.add_cell(Cell::random_cell(rng), rng),
+                MutationOp::Swap => self.swap_cells(rng),
+                MutationOp::Duplicate => self.duplicate_part(rng),
             }
         }
+
+        self.cells_len = self.cells.len();
+        self.update_bounds();
     }
     pub fn add_cell(&mut self, new_cell: Cell, rng: &mut StdRng) {
         if self.cells.is_empty() {
@@ -168,12 +227,12 @@ impl Organism {
         }
     }
     pub fn remove_cell(&mut self, rng: &mut StdRng) {
-        if self.cells_len <= 1 {
+        if self.cells.len() <= 1 {
             return; 
         }
 
         let original_cells = self.cells.clone();
-        let remove_index = rng.gen_range(0..self.cells_len);
+        let remove_index = rng.gen_range(0..self.cells.len());
 
         self.cells.remove(remove_index);
 
@@ -186,9 +245,51 @@ impl Organism {
             return;
         }
 
-        let index = rng.gen_range(0..self.cells_len);
+        let index = rng.gen_range(0..self.cells.len());
         self.cells[index].2 = Cell::random_cell(rng);
     }
+    pub fn swap_cells(&mut self, rng: &mut StdRng) {
+        if self.cells.len() < 2 {
+            return;
+        }
+
+        let a = rng.gen_range(0..self.cells.len());
+        let b = rng.gen_range(0..self.cells.len());
+        let cell_a = self.cells[a].2;
+        self.cells[a].2 = self.cells[b].2;
+        self.cells[b].2 = cell_a;
+    }
+    /// Copies a cell and its neighbours next to the original part, skipping any overlapping positions.
+    pub fn duplicate_part(&mut self, rng: &mut StdRng) {
+        if self.cells.is_empty() {
+            return;
+        }
+
+        let &(base_x, base_y, _) = self.cells.choose(rng).unwrap();
+        let part: Vec<(i32, i32, Cell)> = self.cells
+            .iter()
+            .filter(|(dx, dy, _)| (dx - base_x).abs() <= 1 && (dy - base_y).abs() <= 1)
+            .copied()
+            .collect();
+
+        let part_width = part.iter().map(|(dx, _, _)| *dx).max().unwrap() - part.iter().map(|(dx, _, _)| *dx).min().unwrap() + 1;
+        let part_height = part.iter().map(|(_, dy, _)| *dy).max().unwrap() - part.iter().map(|(_, dy, _)| *dy).min().unwrap() + 1;
+        let dir = Direction::random_direction(rng);
+        let shift_x = dir.x_offset() as i32 * part_width;
+        let shift_y = dir.y_offset() as i32 * part_height;
+
+        let original_cells = self.cells.clone();
+        for (dx, dy, cell) in part {
+            let (new_x, new_y) = (dx + shift_x, dy + shift_y);
+            if !self.cells.iter().any(|(cx, cy, _)| *cx == new_x && *cy == new_y) {
+                self.cells.push((new_x, new_y, cell));
+            }
+        }
+
+        if !self.is_connected() {
+            self.cells = original_cells;
+        }
+    }
     
     pub fn decode_anatomy(encoded: &str) -> Vec<(i32, i32, Cell)> {
         let mut cells = Vec::new();
@@ -206,60 +307,79 @@ impl Organism {
 
         cells
     }
+    /// FNV-1a over the genome frame cells sorted by offset, so the same anatomy gets the same species in every
+    /// build and run whatever order its cells were added in.
+    fn species_hash(&self) -> u64 {
+        const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+        const FNV_PRIME: u64 = 0x100000001b3;
+        let mut cells = self.genome_cells();
+        cells.sort_by_key(|&(dx, dy, _)| (dx, dy));
+        Organism::encode_cells(&cells).bytes().fold(FNV_OFFSET, |hash, byte| (hash ^ byte as u64).wrapping_mul(FNV_PRIME))
+    }
     pub fn encode_anatomy(&self) -> String {
+        Organism::encode_cells(&self.genome_cells())
+    }
+    pub fn encode_cells(cells: &[(i32, i32, Cell)]) -> String {
         let mut anatomy = String::new();
-        for (dx, dy, cell) in &self.cells {
+        for (dx, dy, cell) in cells {
             anatomy.push_str(&format!("{},{},", dx, dy));
             anatomy.push_str(&format!("{:?},", cell));
         }
         anatomy
     }
 
-    pub fn random_offset(&mut self, rng: &mut StdRng) {
-        let rang_tup = self.body_range();
-        let x_range = (4 + rang_tup.0) as i32;
-        let y_range = (4 + rang_tup.1) as i32;
-        let dx = rng.gen_range(-x_range..=x_range);
-        let dy = rng.gen_range(-y_range..=y_range);
-        self.x = (self.x as i32 + dx) as usize;
-        self.y = (self.y as i32 + dy) as usize;
-    }
-
+    /// Rotates the body a quarter turn around its anchor. Eyes turn with the body and `heading` tracks the net rotation from the genome frame.
     pub fn rotate(&mut self, clockwise: bool, grid: &grid::Grid) {
         if self.cells.is_empty() {
             return;
         }
 
-        let sum_x: i32 = self.cells.iter().map(|(dx, _, _)| dx).sum();
-        let sum_y: i32 = self.cells.iter().map(|(_, dy, _)| dy).sum();
-        let center_x = sum_x / self.cells.len() as i32;
-        let center_y = sum_y / self.cells.len() as i32;
-
-        let mut new_cells = Vec::new();
-        for &(dx, dy, cell) in &self.cells {
-            let new_dx;
-            let new_dy;
-
-            if clockwise {
-                new_dx = center_x + (dy - center_y);
-                new_dy = center_y - (dx - center_x);
-            } else {
-                new_dx = center_x - (dy - center_y);
-                new_dy = center_y + (dx - center_x);
-            }
-
-            new_cells.push((new_dx, new_dy, cell));
-        }
+        let new_cells: Vec<(i32, i32, Cell)> = self.cells
+            .iter()
+            .map(|&(dx, dy, cell)| Organism::rotate_cell(dx, dy, cell, clockwise))
+            .collect();
 
         for &(dx, dy, _) in &new_cells {
-            let check_x = (self.x as i32 + dx) as usize;
-            let check_y = (self.y as i32 + dy) as usize;
-            if check_x >= WIDTH || check_y >= HEIGHT || !grid.is_cell_empty(check_x, check_y) {
+            if !self.is_free_for(self.x as i32 + dx, self.y as i32 + dy, grid) {
                 return;
             }
         }
 
         self.cells = new_cells;
+        self.heading = self.heading.rotated(clockwise);
+        self.update_bounds();
+    }
+    fn rotate_cell(dx: i32, dy: i32, cell: Cell, clockwise: bool) -> (i32, i32, Cell) {
+        if clockwise {
+            (-dy, dx, cell.rotated(true))
+        } else {
+            (dy, -dx, cell.rotated(false))
+        }
+    }
+    /// Cells as laid out in the genome frame, independent of the current heading.
+    pub fn genome_cells(&self) -> Vec<(i32, i32, Cell)> {
+        let turns = self.heading.quarter_turns();
+        self.cells
+            .iter()
+            .map(|&(dx, dy, cell)| (0..turns).fold((dx, dy, cell), |(x, y, c), _| Organism::rotate_cell(x, y, c, false)))
+            .collect()
+    }
+    /// Brain input layout matching the sensor vector built in `update`, with eyes keyed by their genome frame offset.
+    fn input_keys(&self) -> Vec<InputKey> {
+        let eyes: Vec<(i32, i32)> = self.genome_cells()
+            .iter()
+            .filter(|(_, _, cell)| matches!(cell, Cell::Eye(_)))
+            .map(|&(dx, dy, _)| (dx, dy))
+            .collect();
+        senses::input_keys(&eyes)
+    }
+    /// A tile is free for this organism if nothing is there or it is one of its own cells.
+    fn is_free_for(&self, x: i32, y: i32, grid: &grid::Grid) -> bool {
+        if x < 0 || y < 0 || x >= WIDTH as i32 || y >= HEIGHT as i32 {
+            return false;
+        }
+        grid.is_cell_empty(x as usize, y as usize) ||
+            self.cells.iter().any(|(dx, dy, _)| self.x as i32 + dx == x && self.y as i32 + dy == y)
     }
     pub fn move_dir(&mut self, dir: Direction, grid: &grid::Grid) {
         if dir == Direction::None {
@@ -291,10 +411,7 @@ impl Organism {
         }
     
         for (dx, dy, _) in self.cells.iter() {
-            let check_x = (new_x as i32 + dx) as usize;
-            let check_y = (new_y as i32 + dy) as usize;
-            
-            if !grid.is_cell_empty(check_x, check_y) {
+            if !self.is_free_for(new_x as i32 + dx, new_y as i32 + dy, grid) {
                 return;
             }
         }
@@ -302,25 +419,46 @@ impl Organism {
         self.x = new_x;
         self.y = new_y;
     }
-    pub fn random_movement(&mut self, grid: &grid::Grid, rng: &mut StdRng) {
+    /// What an organism without a brain does: turn or step in a random direction.
+    fn random_action(rng: &mut StdRng) -> Action {
         if rng.gen::<f32>() < 0.5 {
-            self.rotate(rng.gen::<bool>(), grid);
-            return;
+            Action::Rotate(rng.gen::<bool>())
         } else {
-            let dir = Direction::random_direction(rng);
-            self.move_dir(dir, grid);
+            Action::Move(Direction::random_direction(rng), 0.0)
         }
     }
-    pub fn can_reproduce(&self) -> bool {
-        self.energy as f32 >= self.cells_len as f32 * REPRODUCTION_ENEGRGY_MULTIPLER
+    /// Tiles moved for a move at `speed`: at least one, and up to one per mover cell within the speed limit.
+    fn move_steps(speed: f32, mover_num: usize, max_speed: usize) -> usize {
+        ((speed * mover_num.min(max_speed) as f32).round() as usize).max(1)
+    }
+    /// Carries out an action. The organism's own tiles are lifted from the grid while it moves, so a step is only
+    /// blocked by other organisms and never by its own footprint, then stamped at the new place so organisms
+    /// updated later in the tick cannot move into it.
+    fn act(&mut self, action: Action, mover_num: usize, max_speed: usize, grid: &mut grid::Grid) {
+        grid.vacate(self);
+        match action {
+            Action::Move(dir, speed) => {
+                for _ in 0..Organism::move_steps(speed, mover_num, max_speed) {
+                    self.move_dir(dir, grid);
+                }
+            }
+            Action::Rotate(clockwise) => self.rotate(clockwise, grid),
+            Action::Stay => {}
+            Action::ToggleAttack => self.attacking = !self.attacking,
+        }
+        grid.occupy(self);
+    }
+    pub fn can_reproduce(&self, params: &SimParams) -> bool {
+        self.energy as f32 >= self.cells_len as f32 * params.reproduction_energy_multiplier
     }
     pub fn consume_reproduction_energy(&mut self) {
         self.energy -= self.cells_len as i32;
     }
 
-    pub fn update(&mut self, grid: &mut grid::Grid, rng: &mut StdRng) -> bool {
+    pub fn update(&mut self, grid: &mut grid::Grid, params: &SimParams, plasticity: PlasticityMode, rng: &mut StdRng) -> bool {
         self.lifetime -= 1;
-        self.satiety -= HUNGER_RATE * self.cells_len as f32;
+        self.age += 1;
+        self.satiety -= params.hunger_rate * self.cells_len as f32;
         if self.lifetime <= 0 {
             self.killed = true;
             return false;
@@ -330,46 +468,176 @@ impl Organism {
             return false;
         }
 
-        self.eye_data.clear();
+        self.sensor_data.clear();
+        let max_lifetime = Organism::lifetime_len(&self.cells, params) as f32;
+        let reproduction_energy = self.cells_len as f32 * params.reproduction_energy_multiplier;
+        self.sensor_data.extend(senses::internal_inputs(self.satiety, self.energy as f32 / reproduction_energy, self.lifetime as f32 / max_lifetime));
 
-        let mut will_move: bool = false;
+        let mut mover_num: usize = 0;
         for (dx, dy, cell) in self.cells.iter() {
             let x = (self.x as i32 + dx) as usize;
             let y = (self.y as i32 + dy) as usize;
             match cell {
-                Cell::Mouth => {
-                    if grid.mouth_eat(x, y) {
-                        self.energy += 1;
-                        self.satiety += FOOD_BENEFIT;
-                    }
+                Cell::Mouth if grid.mouth_eat(x, y) => {
+                    self.energy += 1;
+                    self.satiety += params.food_benefit;
+                    self.reward += 1.0;
                 }
                 Cell::Producer => {
-                    grid.produce_food(x, y);
+                    grid.produce_food(x, y, params.producer_rate);
                 }
                 Cell::Mover => {
-                    will_move = true;
+                    mover_num += 1;
                 }
-                Cell::Killer => {
+                Cell::Killer if self.attacking => {
                     grid.killer_activates(x, y, self.id);
                 }
                 Cell::Armor => {}
                 Cell::Eye(eye_type) => {
-                    self.eye_data.push(grid.get_eye_data(x, y, (*eye_type).to_direction()));
+                    self.sensor_data.extend(grid.get_eye_data(x, y, eye_type.to_direction(), self.id, self.species).to_inputs());
                 }
                 Cell::Brain => {}
                 _ => {}
             }
         }
 
-        if will_move {
-            if let Some(ref mut brain) = self.brain {
-                let dir = brain.process_input(self.eye_data.clone());
-                self.move_dir(dir, grid);
-            } else {
-                self.random_movement(grid, rng);
-            }
+        if mover_num > 0 {
+            let action = match self.brain {
+                Some(ref mut brain) => {
+                    let action = brain.process_input(&self.sensor_data);
+                    if plasticity != PlasticityMode::Off {
+                        brain.learn(self.reward);
+                    }
+                    action
+                }
+                None => Organism::random_action(rng),
+            };
+            self.act(action, mover_num, params.max_speed, grid);
         }
+        self.reward = 0.0;
         
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use super::*;
+    use crate::cell::EyeType;
+    use crate::utils::MAX_SPEED;
+
+    fn l_shape() -> Organism {
+        let cells = vec![(0, 0, Cell::Mouth), (1, 0, Cell::Mover), (2, 0, Cell::Eye(EyeType::Right)), (0, 1, Cell::Brain)];
+        Organism::new(20, 20, cells, 0, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1))
+    }
+
+    #[test]
+    fn species_ignore_the_order_cells_were_added_in() {
+        let mut shuffled = l_shape();
+        shuffled.cells.reverse();
+        assert_eq!(shuffled.species_hash(), l_shape().species_hash());
+        assert_eq!(l_shape().species, l_shape().species_hash());
+        shuffled.cells[0].2 = Cell::Armor;
+        assert_ne!(shuffled.species_hash(), l_shape().species_hash());
+    }
+
+    #[test]
+    fn rotation_turns_cells_and_eyes_around_the_anchor() {
+        let grid = grid::Grid::new();
+        let mut organism = l_shape();
+        organism.rotate(true, &grid);
+        assert_eq!(organism.cells, vec![(0, 0, Cell::Mouth), (0, 1, Cell::Mover), (0, 2, Cell::Eye(EyeType::Down)), (-1, 0, Cell::Brain)]);
+        assert_eq!(organism.heading, Direction::Right);
+        assert_eq!(organism.body_range(), (2, 3));
+        assert_eq!((organism.min_x, organism.max_x, organism.min_y, organism.max_y), (1, 0, 0, 2));
+    }
+
+    #[test]
+    fn genome_cells_undo_any_number_of_turns() {
+        let grid = grid::Grid::new();
+        let mut organism = l_shape();
+        let genome = organism.cells.clone();
+        for clockwise in [true, true, false, true, true, true] {
+            organism.rotate(clockwise, &grid);
+            assert_eq!(organism.genome_cells(), genome);
+        }
+        assert_eq!(organism.heading, Direction::Up);
+        assert_eq!(organism.cells, genome);
+        assert_eq!(organism.body_range(), (3, 2));
+    }
+
+    #[test]
+    fn rotation_into_an_occupied_tile_does_nothing() {
+        let mut grid = grid::Grid::new();
+        let mut organism = l_shape();
+        grid.organs[20][19] = Cell::Armor; // where the brain cell would swing to
+        organism.rotate(true, &grid);
+        assert_eq!(organism.cells, l_shape().cells);
+        assert_eq!(organism.heading, Direction::Up);
+    }
+
+    /// A U opening upwards: moving it sideways puts cells on tiles its old footprint had but the current one does not.
+    fn u_shape(x: usize, y: usize, id: usize) -> Organism {
+        let cells = vec![(0, 0, Cell::Mover), (0, 1, Cell::Mover), (1, 1, Cell::Mover), (2, 1, Cell::Mouth), (2, 0, Cell::Mouth)];
+        Organism::new(x, y, cells, id, &SimParams::default(), &mut InnovationTracker::new(), &mut StdRng::seed_from_u64(1))
+    }
+
+    fn footprint(organism: &Organism) -> Vec<(usize, usize)> {
+        organism.cells.iter().map(|(dx, dy, _)| ((organism.x as i32 + dx) as usize, (organism.y as i32 + dy) as usize)).collect()
+    }
+
+    #[test]
+    fn move_steps_scale_with_speed_and_movers_up_to_the_limit() {
+        assert_eq!(Organism::move_steps(0.0, 3, 3), 1);
+        assert_eq!(Organism::move_steps(1.0, 1, 3), 1);
+        assert_eq!(Organism::move_steps(1.0, 2, 3), 2);
+        assert_eq!(Organism::move_steps(0.5, 2, 3), 1);
+        assert_eq!(Organism::move_steps(1.0, 5, 3), 3);
+        assert_eq!(Organism::move_steps(0.6, 5, 3), 2);
+    }
+
+    #[test]
+    fn fast_moves_are_not_blocked_by_the_old_footprint() {
+        let mut grid = grid::Grid::new();
+        let mut organism = u_shape(20, 20, 0);
+        grid.occupy(&organism);
+        organism.act(Action::Move(Direction::Left, 1.0), 3, MAX_SPEED, &mut grid);
+        assert_eq!((organism.x, organism.y), (17, 20));
+
+        let stamped: Vec<(usize, usize)> = (0..HEIGHT).flat_map(|y| (0..WIDTH).map(move |x| (x, y))).filter(|&(x, y)| grid.organs[y][x] != Cell::Empty).collect();
+        let mut expected = footprint(&organism);
+        expected.sort_by_key(|&(x, y)| (y, x));
+        assert_eq!(stamped, expected);
+    }
+
+    #[test]
+    fn fast_moves_stop_next_to_organisms_moved_earlier_in_the_tick() {
+        let mut grid = grid::Grid::new();
+        let mut first = u_shape(20, 20, 0);
+        let mut second = u_shape(26, 20, 1);
+        grid.occupy(&first);
+        grid.occupy(&second);
+        first.act(Action::Move(Direction::Right, 1.0), 3, MAX_SPEED, &mut grid);
+        second.act(Action::Move(Direction::Left, 1.0), 3, MAX_SPEED, &mut grid);
+        assert_eq!(first.x, 23);
+        assert_eq!(second.x, 26, "the second organism has no room left to move into");
+        assert!(footprint(&first).iter().all(|tile| !footprint(&second).contains(tile)));
+    }
+
+    #[test]
+    fn other_actions_keep_the_organism_in_place() {
+        let mut grid = grid::Grid::new();
+        let mut organism = l_shape();
+        grid.occupy(&organism);
+        organism.act(Action::Stay, 1, MAX_SPEED, &mut grid);
+        assert_eq!((organism.x, organism.y, organism.heading), (20, 20, Direction::Up));
+        organism.act(Action::ToggleAttack, 1, MAX_SPEED, &mut grid);
+        assert!(!organism.attacking);
+        organism.act(Action::ToggleAttack, 1, MAX_SPEED, &mut grid);
+        assert!(organism.attacking);
+        organism.act(Action::Rotate(false), 1, MAX_SPEED, &mut grid);
+        assert_eq!((organism.x, organism.y, organism.heading), (20, 20, Direction::Left));
+        assert!(footprint(&organism).iter().all(|&(x, y)| grid.organs[y][x] != Cell::Empty));
+    }
+}
@@ -1,25 +1,61 @@
-use crate::grid::Grid;
+use crate::grid::{Grid, SpawnError};
 use crate::cell::Cell;
 use crate::organism::Organism;
-use crate::utils::{HEIGHT, WIDTH};
-use rand::{Rng, SeedableRng, rngs::StdRng};
+use crate::mutation::{MutationPolicy, MutationRates};
+use crate::params::{self, SimParams, SETTINGS};
+use crate::innovation::InnovationTracker;
+use crate::utils::{HEIGHT, WIDTH, CELL_SIZE, HISTORY_INTERVAL, CHART_SPECIES, GALLERY_SPECIES, CONFIG_FILE, SLIDER_LABEL_SHARE};
+use crate::brain_view;
+use crate::ui;
+use crate::charts::SimHistory;
+use crate::gallery::SpeciesRegistry;
+use crate::overlay::{Heatmaps, RenderMode};
+use crate::camera::WorldCamera;
+use crate::layout::Layout;
+use crate::editor::Editor;
+use crate::designer::Designer;
+use crate::library::GenomeLibrary;
+use crate::capture::Capture;
+use macroquad::prelude::*;
+use ::rand::{Rng, SeedableRng, rngs::StdRng};
 use std::collections::HashMap;
 
+#[derive(Clone)]
 pub struct SimData {
     pub frame: u128,
     pub organism_num: usize,
-    pub best_species: String,
     pub hunger_death: usize,
     pub age_death: usize,
+    pub predation_death: usize,
+    pub food_amount: usize,
+    pub mean_size: f32,
+    pub species_counts: Vec<(u64, usize)>, // living members of the most populous species, largest first
+    pub mean_mutation_rate: f32,
+    pub blocked_births: usize,
+    pub out_of_bounds_births: usize,
+    pub extinctions: usize, // times the population died out and the world was reseeded
 }
+impl Default for SimData {
+    fn default() -> Self {
+        SimData::new()
+    }
+}
+
 impl SimData {
     pub fn new() -> Self {
         SimData {
             frame: 0,
             organism_num: 0,
-            best_species: String::new(),
             hunger_death: 0,
             age_death: 0,
+            predation_death: 0,
+            food_amount: 0,
+            mean_size: 0.0,
+            species_counts: Vec::new(),
+            mean_mutation_rate: 0.0,
+            blocked_births: 0,
+            out_of_bounds_births: 0,
+            extinctions: 0,
         }
     }
 }
@@ -29,85 +65,505 @@ pub struct OrganismManager {
     pub grid: Grid,
     pub frame: u128,
     pub rng: StdRng,
+    pub seed: u64, // the seed the world was started or last reseeded from
+    pub mutation_policy: MutationPolicy,
+    pub params: SimParams,
+    pub innovations: InnovationTracker,
+    pub selected: Option<usize>, // id of the organism shown in the inspection panels
+    pub highlighted_species: Option<u64>, // species picked in the gallery, whose members are outlined
+    pub camera: WorldCamera,
+    pub editor: Editor,
+    pub designer: Designer,
+    pub library: GenomeLibrary,
+    pub render_mode: RenderMode,
+    pub capture: Capture,
     
-    species_success: HashMap<String, usize>,
-    sim_data: SimData
+    next_id: usize,
+    species: SpeciesRegistry,
+    sim_data: SimData,
+    history: SimHistory,
+    heatmaps: Heatmaps,
+    lower_panel: LowerPanel,
+    settings_scroll: usize, // first settings row shown when they do not all fit
+    settings_message: String, // outcome of the last config save, shown in the settings panel
+}
+
+/// What the panel under the sim menu shows.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LowerPanel {
+    Brain,
+    Charts,
+    Settings,
+}
+
+impl Default for OrganismManager {
+    fn default() -> Self {
+        OrganismManager::new()
+    }
 }
 
 impl OrganismManager {
     pub fn new() -> Self {
+        OrganismManager::with_seed(crate::utils::SEED)
+    }
+
+    /// A world whose every random choice follows from `seed`, so the same seed and settings replay the same run.
+    pub fn with_seed(seed: u64) -> Self {
         OrganismManager {
             organisms: Vec::new(),
-            grid: Grid::new(),
+            grid: Grid::with_seed(seed),
             frame: 0,
-            species_success: HashMap::new(),
-            rng: StdRng::seed_from_u64(crate::utils::SEED),
+            species: SpeciesRegistry::new(),
+            rng: StdRng::seed_from_u64(seed),
+            seed,
+            mutation_policy: MutationPolicy::default(),
+            params: SimParams::default(),
+            innovations: InnovationTracker::new(),
+            selected: None,
+            highlighted_species: None,
+            camera: WorldCamera::new(),
+            editor: Editor::new(),
+            designer: Designer::new(),
+            library: GenomeLibrary::new(),
+            render_mode: RenderMode::Cells,
+            capture: Capture::new(),
 
+            next_id: 0,
             sim_data: SimData::new(),
+            history: SimHistory::new(),
+            heatmaps: Heatmaps::new(),
+            lower_panel: LowerPanel::Brain,
+            settings_scroll: 0,
+            settings_message: String::new(),
         }
     }
 
-    fn default_org() -> Vec<(i32, i32, Cell)> {
-        vec![(-1, -1, Cell::Mover), (0, 0, Cell::Mouth), (1, 1, Cell::Producer)]
-        //Organism::new(50, 50, vec![(0, -1, Cell::Mouth),(-1, 0, Cell::Producer), (0, 0, Cell::Mover), (1, 0, Cell::Producer),(-1, 1, Cell::Brain), (0, 1, Cell::Eye(EyeType::Down)), (1, 1, Cell::Brain),], 0)
-    }
-
     pub fn init(&mut self) {
         //self.grid.scatter_food();
-        for i in 0..10 {
-            self.organisms.push(Organism::new(self.rng.gen_range(10..WIDTH - 10), self.rng.gen_range(10..HEIGHT - 10), OrganismManager::default_org(), i, &mut self.rng));
+        for _ in 0..10 {
+            let id = self.assign_id();
+            let organism = Organism::new(self.rng.gen_range(10..WIDTH - 10), self.rng.gen_range(10..HEIGHT - 10), GenomeLibrary::starter(), id, &self.params, &mut self.innovations, &mut self.rng);
+            self.species.record_birth(&organism, self.frame);
+            self.organisms.push(organism);
+        }
+    }
+
+    /// Starts new random streams from `seed` and the current tick, as used for a world loaded from a snapshot.
+    /// The world runs on deterministically from there, though not necessarily as the run that saved it did.
+    pub fn reseed(&mut self, seed: u64) {
+        let stream = seed.wrapping_add((self.frame as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        self.seed = seed;
+        self.rng = StdRng::seed_from_u64(stream);
+        self.grid.rng = StdRng::seed_from_u64(stream.wrapping_add(1));
+    }
+
+    /// Puts the organisms and records of a saved world in place of the current ones and stamps them onto the grid.
+    /// Only the cumulative counters are taken from `counters`, the rest are recomputed.
+    pub fn restore(&mut self, organisms: Vec<Organism>, species: SpeciesRegistry, counters: SimData, next_id: usize) {
+        self.organisms = organisms;
+        self.species = species;
+        self.sim_data = counters;
+        self.next_id = next_id;
+        self.sim_data.frame = self.frame;
+        self.sim_data.organism_num = self.organisms.len();
+        self.sim_data.mean_mutation_rate = self.organisms.iter().map(|organism| organism.mutation_rates.anatomy).sum::<f32>() / self.organisms.len().max(1) as f32;
+        self.grid.restamp(&self.organisms);
+    }
+
+    pub fn next_id(&self) -> usize {
+        self.next_id
+    }
+
+    pub fn assign_id(&mut self) -> usize {
+        self.next_id += 1;
+        self.next_id - 1
+    }
+
+    /// Adds a fresh organism with the given anatomy anchored at (x, y) if all of its cells fit there. Returns its id.
+    pub fn spawn_organism(&mut self, cells: Vec<(i32, i32, Cell)>, x: usize, y: usize) -> Result<usize, SpawnError> {
+        let organism = Organism::new(x, y, cells, self.next_id, &self.params, &mut self.innovations, &mut self.rng);
+        self.grid.check_spawn(&organism, x as i32, y as i32)?;
+        self.next_id += 1;
+        self.grid.occupy(&organism);
+        self.species.record_birth(&organism, self.frame);
+        self.organisms.push(organism);
+        Ok(self.next_id - 1)
+    }
+
+    /// Places an unmutated copy of an organism next to it. Returns the copy's id, or None if no organism has that id.
+    pub fn clone_organism(&mut self, id: usize) -> Option<Result<usize, SpawnError>> {
+        let original = self.organisms.iter().find(|organism| organism.id == id)?;
+        let mut copy = original.clone_as(self.next_id, &self.params);
+        let (x, y) = match self.grid.find_spawn(&copy, original.x, original.y, &mut self.rng) {
+            Ok(position) => position,
+            Err(error) => return Some(Err(error)),
+        };
+        copy.x = x;
+        copy.y = y;
+        self.next_id += 1;
+        self.grid.occupy(&copy);
+        self.species.record_birth(&copy, self.frame);
+        self.organisms.push(copy);
+        Some(Ok(self.next_id - 1))
+    }
+
+    /// Takes an organism out of the world without leaving remains. Returns false if no organism has that id.
+    pub fn remove_organism(&mut self, id: usize) -> bool {
+        self.remove_organisms(&[id]) > 0
+    }
+
+    /// Takes several organisms out of the world at once, restamping the grid a single time. Returns how many were removed.
+    pub fn remove_organisms(&mut self, ids: &[usize]) -> usize {
+        let len = self.organisms.len();
+        self.organisms.retain(|organism| !ids.contains(&organism.id));
+        self.grid.restamp(&self.organisms);
+        len - self.organisms.len()
+    }
+
+    /// Ids of the organisms with at least one cell inside the inclusive tile rectangle.
+    pub fn organisms_in_region(&self, (min_x, min_y): (usize, usize), (max_x, max_y): (usize, usize)) -> Vec<usize> {
+        self.organisms
+            .iter()
+            .filter(|organism| organism.cells.iter().any(|(dx, dy, _)| {
+                let (x, y) = (organism.x as i32 + dx, organism.y as i32 + dy);
+                x >= min_x as i32 && x <= max_x as i32 && y >= min_y as i32 && y <= max_y as i32
+            }))
+            .map(|organism| organism.id)
+            .collect()
+    }
+
+    pub fn selected_organism(&self) -> Option<&Organism> {
+        self.selected.and_then(|id| self.organisms.iter().find(|organism| organism.id == id))
+    }
+
+    /// Clicking the world selects the organism under the cursor or clears the selection on an empty tile.
+    /// Tab cycles through brained organisms and Escape deselects.
+    fn update_selection(&mut self) {
+        if self.selected_organism().is_none() {
+            self.selected = None;
+        }
+
+        if is_mouse_button_pressed(MouseButton::Left) {
+            let (mouse_x, mouse_y) = mouse_position();
+            if WorldCamera::contains_screen(mouse_x, mouse_y) && !self.over_world_widgets(mouse_x, mouse_y) {
+                self.selected = self.grid.organism_at_screen(mouse_x, mouse_y, &self.camera);
+            }
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            let brained: Vec<usize> = self.organisms.iter().filter(|organism| organism.brain.is_some()).map(|organism| organism.id).collect();
+            let current = self.selected.and_then(|id| brained.iter().position(|brained_id| *brained_id == id));
+            if !brained.is_empty() {
+                self.selected = Some(brained[current.map_or(0, |i| (i + 1) % brained.len())]);
+            }
+        }
+        if is_key_pressed(KeyCode::Escape) {
+            self.selected = None;
+        }
+    }
+
+    /// The most populous living species in the box at the top of the menu. Clicking one outlines its members on the map.
+    fn draw_species_gallery(&mut self, area: Rect) {
+        let mut ranking = SpeciesRegistry::living_counts(&self.organisms);
+        ranking.truncate(GALLERY_SPECIES);
+        if let Some(species) = self.species.draw_gallery(&ranking, self.highlighted_species, area, 3) {
+            self.highlighted_species = if self.highlighted_species == Some(species) { None } else { Some(species) };
+        }
+    }
+
+    fn draw_inspector(&self) {
+        let Some(organism) = self.selected_organism() else {
+            return;
+        };
+        self.grid.draw_highlight(organism, &self.camera, WHITE);
+
+        let (x, y, width, height) = (CELL_SIZE, CELL_SIZE, 260.0, 340.0);
+        draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.75));
+        Grid::draw_anatomy(&organism.cells, x + width / 2.0, y + 70.0, CELL_SIZE);
+
+        let lines = [
+            format!("Organism #{}", organism.id),
+            format!("Species: {:08x}", organism.species as u32),
+            format!("Species births: {}", self.species.get(organism.species).map_or(0, |record| record.births)),
+            format!("Age: {}", organism.age),
+            format!("Lifetime left: {}", organism.lifetime),
+            format!("Energy: {}", organism.energy),
+            format!("Satiety: {:.2}", organism.satiety),
+            format!("Cells: {}", organism.cells.len()),
+            format!("Parent: {}", organism.parent.map_or("none".to_owned(), |parent| format!("#{}", parent))),
+            format!("Offspring: {}", organism.offspring),
+            format!("Follow (F): {}", if self.camera.follow { "on" } else { "off" }),
+        ];
+        for (i, line) in lines.iter().enumerate() {
+            draw_text(line.as_str(), x + CELL_SIZE, y + 160.0 + i as f32 * 16.0, 18.0, WHITE);
+        }
+    }
+
+    /// The panel under the menu shows the selected organism's brain, the history charts or the settings.
+    /// H toggles the charts and O the settings.
+    fn draw_lower_panel(&mut self, area: Rect) {
+        let tab_height = 24.0;
+        let toggle = |panel: LowerPanel, current: LowerPanel| if current == panel { LowerPanel::Brain } else { panel };
+        if is_key_pressed(KeyCode::H) {
+            self.lower_panel = toggle(LowerPanel::Charts, self.lower_panel);
+        }
+        if is_key_pressed(KeyCode::O) {
+            self.lower_panel = toggle(LowerPanel::Settings, self.lower_panel);
+        }
+        let tabs = [("Brain", LowerPanel::Brain), ("Charts (H)", LowerPanel::Charts), ("Settings (O)", LowerPanel::Settings)];
+        let tab_width = area.w / tabs.len() as f32;
+        for (i, (label, panel)) in tabs.into_iter().enumerate() {
+            if ui::button(area.x + i as f32 * tab_width, area.y, tab_width, tab_height, label, self.lower_panel == panel) {
+                self.lower_panel = panel;
+            }
         }
+
+        let panel = Rect::new(area.x, area.y + tab_height, area.w, (area.h - tab_height).max(0.0));
+        match self.lower_panel {
+            LowerPanel::Brain => self.draw_brain_panel(panel),
+            LowerPanel::Charts => self.history.draw(panel.x, panel.y, panel.w, panel.h),
+            LowerPanel::Settings => self.draw_settings_panel(panel),
+        }
+    }
+
+    fn view_button() -> Rect {
+        let width = 200.0;
+        Rect::new(WorldCamera::viewport().0 - width - CELL_SIZE, CELL_SIZE, width, 24.0)
+    }
+
+    /// Whether a screen position is over one of the buttons drawn on top of the world view.
+    pub fn over_world_widgets(&self, screen_x: f32, screen_y: f32) -> bool {
+        self.editor.over_toolbar(screen_x, screen_y) || OrganismManager::view_button().contains(vec2(screen_x, screen_y))
+    }
+
+    /// V cycles the render mode, as does the button in the top right corner of the world view.
+    fn update_render_mode(&mut self) {
+        if !self.grid.graphics_on() {
+            return;
+        }
+        let button = OrganismManager::view_button();
+        if is_key_pressed(KeyCode::V) || ui::button(button.x, button.y, button.w, button.h, format!("View: {} (V)", self.render_mode.label()).as_str(), false) {
+            self.render_mode = self.render_mode.next();
+        }
+    }
+
+    /// Sliders for every runtime setting, plus buttons to restore the defaults and to save them as a config file.
+    /// Rows that do not fit above the buttons scroll with the mouse wheel over their labels.
+    fn draw_settings_panel(&mut self, area: Rect) {
+        let (x, y) = (area.x, area.y);
+        draw_rectangle(x, y, area.w, area.h, Color::new(0.12, 0.12, 0.14, 1.0));
+        let row_height = 20.0;
+        let button_y = y + area.h - 30.0;
+        let rows_height = button_y - 24.0 - y; // leaves a line for the save message above the buttons
+        let visible_rows = ((rows_height / row_height) as usize).clamp(1, SETTINGS.len());
+        let max_scroll = SETTINGS.len() - visible_rows;
+        let slider_width = area.w - 2.0 * CELL_SIZE;
+
+        let (mouse_x, mouse_y) = mouse_position();
+        let wheel = mouse_wheel().1;
+        if wheel != 0.0 && ui::contains(x, y, CELL_SIZE + slider_width * SLIDER_LABEL_SHARE, rows_height, mouse_x, mouse_y) {
+            self.settings_scroll = if wheel > 0.0 { self.settings_scroll.saturating_sub(1) } else { self.settings_scroll + 1 };
+        }
+        self.settings_scroll = self.settings_scroll.min(max_scroll);
+
+        let seed_rates = self.params.seed_mutation_rates();
+        for (row, setting) in SETTINGS.iter().skip(self.settings_scroll).take(visible_rows).enumerate() {
+            let value = (setting.get)(&self.params, &self.mutation_policy);
+            let row_y = y + 4.0 + row as f32 * row_height;
+            if let Some(value) = ui::slider(Rect::new(x + CELL_SIZE, row_y, slider_width, row_height), setting.label, value, (setting.min, setting.max), setting.integer) {
+                (setting.set)(&mut self.params, &mut self.mutation_policy, value);
+            }
+        }
+        if max_scroll > 0 {
+            let track = visible_rows as f32 * row_height;
+            let thumb = track * visible_rows as f32 / SETTINGS.len() as f32;
+            let thumb_y = y + 4.0 + (track - thumb) * self.settings_scroll as f32 / max_scroll as f32;
+            draw_rectangle(x + area.w - 5.0, thumb_y, 3.0, thumb, GRAY);
+        }
+
+        let button_width = (area.w - 3.0 * CELL_SIZE) / 2.0;
+        if ui::button(x + CELL_SIZE, button_y, button_width, 24.0, "Reset defaults", false) {
+            self.params = SimParams::default();
+            self.mutation_policy = MutationPolicy::default();
+        }
+        self.rescale_mutation_rates(seed_rates);
+        if ui::button(x + 2.0 * CELL_SIZE + button_width, button_y, button_width, 24.0, "Save config", false) {
+            self.settings_message = match params::save_config(CONFIG_FILE, &self.params, &self.mutation_policy) {
+                Ok(()) => format!("Saved to {}", CONFIG_FILE),
+                Err(error) => format!("Could not save {}: {}", CONFIG_FILE, error),
+            };
+        }
+        draw_text(self.settings_message.as_str(), x + CELL_SIZE, button_y - 6.0, 16.0, WHITE);
     }
 
-    pub fn assign_id(&self) -> usize {
-        self.organisms.len()
+    /// Moves every living organism's heritable mutation rates along with a change of the seed rates from `old`.
+    pub fn rescale_mutation_rates(&mut self, old: MutationRates) {
+        let new = self.params.seed_mutation_rates();
+        if new == old {
+            return;
+        }
+        for organism in self.organisms.iter_mut() {
+            organism.mutation_rates = organism.mutation_rates.rescaled(&old, &new);
+        }
+    }
+
+    fn draw_brain_panel(&self, area: Rect) {
+        let (x, y) = (area.x, area.y);
+        match self.selected_organism().and_then(|organism| organism.brain.as_ref().map(|brain| (organism.id, brain))) {
+            Some((id, brain)) => {
+                brain_view::draw_brain(brain, x, y, area.w, area.h);
+                draw_text(format!("Brain of #{} (Tab: next)", id).as_str(), x + CELL_SIZE, y + 16.0, 18.0, WHITE);
+            }
+            None => {
+                draw_rectangle(x, y, area.w, area.h, DARKGRAY);
+                draw_text("Select a brained organism (Tab)", x + CELL_SIZE, y + 16.0, 18.0, WHITE);
+            }
+        }
     }
 
-    pub fn update(&mut self) {
-        self.grid.update(&mut self.organisms);
+    /// Advances the simulation by one tick without drawing anything.
+    pub fn tick(&mut self) {
+        self.grid.update(&mut self.organisms, self.params.damage_penalty);
         self.organisms.retain(|organism| {
             if organism.killed {
+            let predation = organism.lifetime > 0 && organism.satiety > 0.0;
             if organism.lifetime <= 0 {
                 self.sim_data.age_death += 1;
-            } else {
+            } else if organism.satiety <= 0.0 {
                 self.sim_data.hunger_death += 1;
+            } else {
+                self.sim_data.predation_death += 1;
             }
-            self.grid.make_remains(organism);
+            self.heatmaps.record_death(organism, predation);
+            self.grid.make_remains(organism, self.params.drop_food_rate);
             false
             } else {
             true
             }
         });
         
-        let mut next_id = self.assign_id();
-
         let mut new_organisms = Vec::new();
         for organism in self.organisms.iter_mut() {
-            organism.update(&mut self.grid, &mut self.rng);
-            if organism.can_reproduce() {
-                let mut new_org = organism.child(next_id, &mut self.rng); next_id += 1;
-                new_org.random_offset(&mut self.rng);
-                if self.grid.check_spawn(&new_org) {
-                    new_organisms.push(new_org);
-                    self.species_success.insert(organism.encode_anatomy(), self.species_success.get(&organism.encode_anatomy()).unwrap_or(&0) + 1);
+            organism.update(&mut self.grid, &self.params, self.mutation_policy.plasticity, &mut self.rng);
+            if organism.can_reproduce(&self.params) {
+                // the brain is only built once the mutated anatomy has found room, a blocked parent retries every tick
+                let mut new_org = organism.child(self.next_id, &self.params, &self.mutation_policy, &mut self.rng);
+                match self.grid.find_spawn(&new_org, organism.x, organism.y, &mut self.rng) {
+                    Ok((x, y)) => {
+                        self.next_id += 1;
+                        new_org.x = x;
+                        new_org.y = y;
+                        new_org.inherit_brain(organism, &self.mutation_policy, &mut self.innovations, &mut self.rng);
+                        self.grid.occupy(&new_org);
+                        self.species.record_birth(&new_org, self.frame);
+                        new_organisms.push(new_org);
+                        organism.consume_reproduction_energy();
+                        organism.offspring += 1;
+                        organism.birth_blocked = false;
+                    }
+                    Err(_) if organism.birth_blocked => {}
+                    Err(error) => {
+                        organism.birth_blocked = true;
+                        match error {
+                            SpawnError::OutOfBounds => self.sim_data.out_of_bounds_births += 1,
+                            SpawnError::Blocked => self.sim_data.blocked_births += 1,
+                        }
+                    }
                 }
-                organism.consume_reproduction_energy();
             }
         }
         self.organisms.extend(new_organisms);
 
         if self.organisms.is_empty() {
             //self.grid.foods = [[false; WIDTH]; HEIGHT];
+            self.sim_data.extinctions += 1;
             self.init();
         }
 
-        if let Some((species, _success)) = self.species_success.iter().max_by_key(|entry| entry.1) {
-            self.sim_data.best_species = species.clone();
-        }
-        
-        if crate::utils::GRAPHICS {self.grid.update_sim_menu(&self.sim_data);}
 
         self.frame += 1;
+        self.sim_data.frame = self.frame;
         self.sim_data.organism_num = self.organisms.len();
+        self.sim_data.mean_mutation_rate = self.organisms.iter().map(|organism| organism.mutation_rates.anatomy).sum::<f32>() / self.organisms.len().max(1) as f32;
+        if self.frame.is_multiple_of(HISTORY_INTERVAL) {
+            self.record_history();
+            self.heatmaps.record_food(&self.grid);
+        }
+        self.record_frame();
+    }
+
+    /// Fills in the statistics that are only needed for the charts and stores a copy of `sim_data`.
+    fn record_history(&mut self) {
+        self.sim_data = self.current_stats();
+        self.history.push(self.sim_data.clone());
+    }
+
+    /// `sim_data` with the chart-only statistics, which are otherwise only updated every `HISTORY_INTERVAL` ticks,
+    /// computed for the current tick.
+    pub fn current_stats(&self) -> SimData {
+        let mut stats = self.sim_data.clone();
+        stats.food_amount = self.grid.food_count();
+        stats.mean_size = self.organisms.iter().map(|organism| organism.cells.len()).sum::<usize>() as f32 / self.organisms.len().max(1) as f32;
+
+        let mut counts = SpeciesRegistry::living_counts(&self.organisms);
+        counts.truncate(CHART_SPECIES);
+        stats.species_counts = counts;
+        stats
+    }
+
+    pub fn species(&self) -> &SpeciesRegistry {
+        &self.species
+    }
+
+    pub fn history(&self) -> &SimHistory {
+        &self.history
+    }
+
+    /// Colours of the organisms the render mode draws in a single colour, keyed by id.
+    pub fn organism_colors(&self) -> HashMap<usize, Color> {
+        self.organisms
+            .iter()
+            .filter_map(|organism| self.render_mode.organism_color(organism, &self.params).map(|color| (organism.id, color)))
+            .collect()
+    }
+
+    /// Renders the world and the side panels and handles their mouse and keyboard input.
+    pub fn draw(&mut self) {
+        if !crate::utils::GRAPHICS {
+            return;
+        }
+        let layout = Layout::current();
+        let (mouse_x, mouse_y) = mouse_position();
+        let over_designer = self.designer.contains_screen(mouse_x, mouse_y);
+        self.camera.fit_to_window();
+        if !over_designer {
+            self.camera.handle_input();
+        }
+        if self.camera.follow {
+            if let Some(organism) = self.selected_organism() {
+                let (x, y) = (organism.x as f32 + 0.5, organism.y as f32 + 0.5);
+                self.camera.center_on(x, y);
+            }
+        }
+        self.grid.draw(&self.camera, &self.organism_colors());
+        self.heatmaps.draw(self.render_mode, &self.camera);
+        if let Some(species) = self.highlighted_species {
+            for organism in self.organisms.iter().filter(|organism| organism.species == species) {
+                self.grid.draw_highlight(organism, &self.camera, YELLOW);
+            }
+        }
+        self.update_render_mode();
+        if self.editor.active && !over_designer {
+            self.update_editor();
+        }
+        self.grid.update_sim_menu(&self.sim_data, &layout);
+        self.draw_species_gallery(layout.gallery);
+        if !self.editor.active && !over_designer {
+            self.update_selection();
+        }
+        self.draw_inspector();
+        self.draw_lower_panel(layout.lower);
+        self.draw_editor_toolbar();
+        self.update_capture();
+        self.update_designer();
     }
 }
\ No newline at end of file
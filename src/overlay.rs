@@ -0,0 +1,157 @@
+use macroquad::prelude::*;
+use crate::camera::WorldCamera;
+use crate::charts::species_color;
+use crate::grid::Grid;
+use crate::organism::Organism;
+use crate::params::SimParams;
+use crate::utils::{WIDTH, HEIGHT};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RenderMode {
+    Cells,
+    Species,
+    Energy,
+    Age,
+    Lineage,
+    Brain,
+    FoodHeat,
+    DeathHeat,
+    KillHeat,
+}
+
+impl RenderMode {
+    pub const ALL: [RenderMode; 9] = [
+        RenderMode::Cells,
+        RenderMode::Species,
+        RenderMode::Energy,
+        RenderMode::Age,
+        RenderMode::Lineage,
+        RenderMode::Brain,
+        RenderMode::FoodHeat,
+        RenderMode::DeathHeat,
+        RenderMode::KillHeat,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RenderMode::Cells => "Cells",
+            RenderMode::Species => "Species",
+            RenderMode::Energy => "Energy",
+            RenderMode::Age => "Age",
+            RenderMode::Lineage => "Lineage",
+            RenderMode::Brain => "Brain",
+            RenderMode::FoodHeat => "Food heatmap",
+            RenderMode::DeathHeat => "Death heatmap",
+            RenderMode::KillHeat => "Kill heatmap",
+        }
+    }
+
+    pub fn next(self) -> RenderMode {
+        let index = RenderMode::ALL.iter().position(|mode| *mode == self).unwrap_or(0);
+        RenderMode::ALL[(index + 1) % RenderMode::ALL.len()]
+    }
+
+    /// Colour of a whole organism in this mode, or None to draw it by cell type.
+    pub fn organism_color(self, organism: &Organism, params: &SimParams) -> Option<Color> {
+        match self {
+            RenderMode::Species => Some(species_color(organism.species)),
+            RenderMode::Energy => {
+                let reproduction_energy = organism.cells.len() as f32 * params.reproduction_energy_multiplier;
+                Some(gradient(organism.energy as f32 / reproduction_energy))
+            }
+            RenderMode::Age => {
+                let lifetime = organism.cells.len() as f32 * params.lifetime_multiplier as f32;
+                Some(gradient(organism.age as f32 / lifetime))
+            }
+            RenderMode::Lineage => Some(species_color((organism.lineage as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15))),
+            RenderMode::Brain => Some(if organism.brain.is_some() { PINK } else { GRAY }),
+            _ => None,
+        }
+    }
+}
+
+/// Blue for 0 through green to red for 1.
+pub fn gradient(value: f32) -> Color {
+    let value = value.clamp(0.0, 1.0);
+    if value < 0.5 {
+        Color::new(0.2, 0.2 + 1.6 * value, 1.0 - 1.6 * value, 1.0)
+    } else {
+        Color::new(0.2 + 1.6 * (value - 0.5), 1.0 - 1.6 * (value - 0.5), 0.2, 1.0)
+    }
+}
+
+/// Per tile totals of food presence, deaths and kills, accumulated since the world started.
+pub struct Heatmaps {
+    food: Vec<f32>,
+    deaths: Vec<f32>,
+    kills: Vec<f32>,
+}
+
+impl Default for Heatmaps {
+    fn default() -> Self {
+        Heatmaps::new()
+    }
+}
+
+impl Heatmaps {
+    pub fn new() -> Self {
+        Heatmaps {
+            food: vec![0.0; WIDTH * HEIGHT],
+            deaths: vec![0.0; WIDTH * HEIGHT],
+            kills: vec![0.0; WIDTH * HEIGHT],
+        }
+    }
+
+    pub fn record_food(&mut self, grid: &Grid) {
+        for (y, row) in grid.foods.iter().enumerate() {
+            for (x, food) in row.iter().enumerate() {
+                if *food {
+                    self.food[y * WIDTH + x] += 1.0;
+                }
+            }
+        }
+    }
+
+    /// Adds a death on every tile of the organism's body, and a kill as well if it was killed by a predator.
+    pub fn record_death(&mut self, organism: &Organism, predation: bool) {
+        for (dx, dy, _) in organism.cells.iter() {
+            let (x, y) = (organism.x as i32 + dx, organism.y as i32 + dy);
+            if !Grid::in_bounds(x, y) {
+                continue;
+            }
+            let index = y as usize * WIDTH + x as usize;
+            self.deaths[index] += 1.0;
+            if predation {
+                self.kills[index] += 1.0;
+            }
+        }
+    }
+
+    /// Tints the visible tiles by the chosen heatmap, scaled to its largest value. Other modes draw nothing.
+    pub fn draw(&self, mode: RenderMode, camera: &WorldCamera) {
+        let values = match mode {
+            RenderMode::FoodHeat => &self.food,
+            RenderMode::DeathHeat => &self.deaths,
+            RenderMode::KillHeat => &self.kills,
+            _ => return,
+        };
+        let max = values.iter().fold(0.0f32, |max, value| max.max(*value));
+        if max <= 0.0 {
+            return;
+        }
+
+        let ((min_x, max_x), (min_y, max_y)) = camera.visible_tiles();
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let value = values[y * WIDTH + x];
+                if value <= 0.0 {
+                    continue;
+                }
+                let heat = (value / max).sqrt(); // sqrt keeps rarely visited tiles visible next to hot spots
+                let color = gradient(heat);
+                let (screen_x, screen_y) = camera.world_to_screen(x as f32, y as f32);
+                draw_rectangle(screen_x, screen_y, camera.zoom, camera.zoom, Color::new(color.r, color.g, color.b, 0.3 + 0.5 * heat));
+            }
+        }
+    }
+}
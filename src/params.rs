@@ -0,0 +1,161 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::brain::PlasticityMode;
+use crate::mutation::{MutationPolicy, MutationRates};
+use crate::utils::{PRODUCER_RATE, FOOD_BENEFIT, HUNGER_RATE, DROP_FOOD_RATE, LIFETIME_MULTIPLIER, REPRODUCTION_ENEGRGY_MULTIPLER,
+    DAMAGE_PENALTY, MAX_SPEED, MUTATION_RATE, WEIGHT_MUTATION_PROB, WEIGHT_MUTATION_STD};
+
+/// Simulation parameters that can be changed while the world runs. The defaults are the constants in `utils`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimParams {
+    pub producer_rate: f32, // chance per tick that a producer cell grows food next to it
+    pub food_benefit: f32, // satiety gained per food eaten
+    pub hunger_rate: f32, // satiety lost per cell per tick
+    pub drop_food_rate: f32, // chance for each cell of a dead organism to leave food
+    pub lifetime_multiplier: i32, // ticks of life per cell
+    pub reproduction_energy_multiplier: f32, // energy per cell needed to reproduce
+    pub damage_penalty: f32, // reward lost when armor is hit
+    pub max_speed: usize, // most tiles a brained organism can move per tick, one per mover cell
+    pub mutation_rate: f32, // anatomy mutation rate of newly seeded organisms
+    pub weight_prob: f32, // brain weight perturbation chance of newly seeded organisms
+    pub weight_std: f32, // brain weight perturbation size of newly seeded organisms
+}
+
+impl Default for SimParams {
+    fn default() -> Self {
+        SimParams {
+            producer_rate: PRODUCER_RATE,
+            food_benefit: FOOD_BENEFIT,
+            hunger_rate: HUNGER_RATE,
+            drop_food_rate: DROP_FOOD_RATE,
+            lifetime_multiplier: LIFETIME_MULTIPLIER,
+            reproduction_energy_multiplier: REPRODUCTION_ENEGRGY_MULTIPLER,
+            damage_penalty: DAMAGE_PENALTY,
+            max_speed: MAX_SPEED,
+            mutation_rate: MUTATION_RATE,
+            weight_prob: WEIGHT_MUTATION_PROB,
+            weight_std: WEIGHT_MUTATION_STD,
+        }
+    }
+}
+
+impl SimParams {
+    /// Heritable mutation rates given to newly seeded organisms.
+    pub fn seed_mutation_rates(&self) -> MutationRates {
+        MutationRates { anatomy: self.mutation_rate, weight_prob: self.weight_prob, weight_std: self.weight_std }
+    }
+}
+
+/// A tunable value in `SimParams` or `MutationPolicy`, as shown in the settings panel and written to config files.
+pub struct Setting {
+    pub key: &'static str,
+    pub label: &'static str,
+    pub min: f32,
+    pub max: f32,
+    pub integer: bool,
+    pub get: fn(&SimParams, &MutationPolicy) -> f32,
+    pub set: fn(&mut SimParams, &mut MutationPolicy, f32),
+}
+
+pub const SETTINGS: [Setting; 15] = [
+    Setting { key: "producer_rate", label: "Producer rate", min: 0.0, max: 0.3, integer: false,
+        get: |params, _| params.producer_rate, set: |params, _, value| params.producer_rate = value },
+    Setting { key: "food_benefit", label: "Food benefit", min: 0.0, max: 1.0, integer: false,
+        get: |params, _| params.food_benefit, set: |params, _, value| params.food_benefit = value },
+    Setting { key: "hunger_rate", label: "Hunger rate", min: 0.0, max: 0.02, integer: false,
+        get: |params, _| params.hunger_rate, set: |params, _, value| params.hunger_rate = value },
+    Setting { key: "drop_food_rate", label: "Remains food", min: 0.0, max: 1.0, integer: false,
+        get: |params, _| params.drop_food_rate, set: |params, _, value| params.drop_food_rate = value },
+    Setting { key: "lifetime_multiplier", label: "Lifetime/cell", min: 1.0, max: 100.0, integer: true,
+        get: |params, _| params.lifetime_multiplier as f32, set: |params, _, value| params.lifetime_multiplier = value as i32 },
+    Setting { key: "reproduction_energy_multiplier", label: "Birth energy", min: 0.5, max: 5.0, integer: false,
+        get: |params, _| params.reproduction_energy_multiplier, set: |params, _, value| params.reproduction_energy_multiplier = value },
+    Setting { key: "damage_penalty", label: "Damage penalty", min: 0.0, max: 5.0, integer: false,
+        get: |params, _| params.damage_penalty, set: |params, _, value| params.damage_penalty = value },
+    Setting { key: "max_speed", label: "Max speed", min: 1.0, max: 10.0, integer: true,
+        get: |params, _| params.max_speed as f32, set: |params, _, value| params.max_speed = value as usize },
+    Setting { key: "mutation_rate", label: "Mutation rate", min: 0.0, max: 5.0, integer: false,
+        get: |params, _| params.mutation_rate, set: |params, _, value| params.mutation_rate = value },
+    Setting { key: "weight_prob", label: "Weight mut. chance", min: 0.0, max: 1.0, integer: false,
+        get: |params, _| params.weight_prob, set: |params, _, value| params.weight_prob = value },
+    Setting { key: "weight_std", label: "Weight mut. size", min: 0.0, max: 2.0, integer: false,
+        get: |params, _| params.weight_std, set: |params, _, value| params.weight_std = value },
+    Setting { key: "rate_adaptation", label: "Rate adaptation", min: 0.0, max: 1.0, integer: false,
+        get: |_, policy| policy.rate_adaptation, set: |_, policy, value| policy.rate_adaptation = value },
+    Setting { key: "add_connection_prob", label: "Add connection", min: 0.0, max: 1.0, integer: false,
+        get: |_, policy| policy.add_connection_prob, set: |_, policy, value| policy.add_connection_prob = value },
+    Setting { key: "add_node_prob", label: "Add neuron", min: 0.0, max: 1.0, integer: false,
+        get: |_, policy| policy.add_node_prob, set: |_, policy, value| policy.add_node_prob = value },
+    // 0 off, 1 Baldwinian, 2 Lamarckian
+    Setting { key: "plasticity", label: "Plasticity 0/B/L", min: 0.0, max: 2.0, integer: true,
+        get: |_, policy| policy.plasticity.index() as f32, set: |_, policy, value| policy.plasticity = PlasticityMode::from_index(value.round() as usize) },
+];
+
+/// Every setting as a `key = value` line, in the format `parse_config` reads.
+pub fn config_text(params: &SimParams, policy: &MutationPolicy) -> String {
+    let mut config = String::new();
+    for setting in SETTINGS.iter() {
+        config.push_str(&format!("{} = {}\n", setting.key, (setting.get)(params, policy)));
+    }
+    config
+}
+
+pub fn save_config(path: impl AsRef<Path>, params: &SimParams, policy: &MutationPolicy) -> io::Result<()> {
+    fs::write(path, String::from("# Organisms simulation parameters\n") + &config_text(params, policy))
+}
+
+/// Applies the `key = value` lines of a config. Blank lines, `#` comments and unknown keys are ignored,
+/// and values are clamped to each setting's range.
+pub fn parse_config(text: &str, params: &mut SimParams, policy: &mut MutationPolicy) {
+    for line in text.lines() {
+        let Some((key, value)) = config_line(line) else {
+            continue;
+        };
+        let (Some(setting), Ok(value)) = (SETTINGS.iter().find(|setting| setting.key == key), value.parse::<f32>()) else {
+            continue;
+        };
+        (setting.set)(params, policy, value.clamp(setting.min, setting.max));
+    }
+}
+
+pub fn load_config(path: impl AsRef<Path>, params: &mut SimParams, policy: &mut MutationPolicy) -> io::Result<()> {
+    parse_config(&fs::read_to_string(path)?, params, policy);
+    Ok(())
+}
+
+/// The trimmed key and value of a `key = value` line, or None for blank lines, comments and other text.
+pub fn config_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    line.split_once('=').map(|(key, value)| (key.trim(), value.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_text_round_trips_through_parse_config() {
+        let params = SimParams { producer_rate: 0.125, food_benefit: 0.5, lifetime_multiplier: 40, max_speed: 3, mutation_rate: 2.5, weight_std: 0.75, ..SimParams::default() };
+        let policy = MutationPolicy { rate_adaptation: 0.25, add_node_prob: 0.75, plasticity: PlasticityMode::Lamarckian, ..MutationPolicy::default() };
+
+        let (mut parsed_params, mut parsed_policy) = (SimParams::default(), MutationPolicy { plasticity: PlasticityMode::Off, ..MutationPolicy::default() });
+        parse_config(&config_text(&params, &policy), &mut parsed_params, &mut parsed_policy);
+        assert_eq!(parsed_params, params);
+        assert_eq!(parsed_policy.plasticity, PlasticityMode::Lamarckian);
+        assert_eq!(config_text(&parsed_params, &parsed_policy), config_text(&params, &policy));
+    }
+
+    #[test]
+    fn parse_config_clamps_values_and_skips_other_lines() {
+        let (mut params, mut policy) = (SimParams::default(), MutationPolicy::default());
+        parse_config("# comment\n\nproducer_rate = 9\n  max_speed=0  \nunknown = 1\nfood_benefit = lots\nplasticity = 7\n", &mut params, &mut policy);
+        assert_eq!(params.producer_rate, 0.3);
+        assert_eq!(params.max_speed, 1);
+        assert_eq!(params.food_benefit, SimParams::default().food_benefit);
+        assert_eq!(policy.plasticity, PlasticityMode::Lamarckian);
+    }
+}
@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter};
+use std::path::Path;
+use macroquad::color::{Color, BLACK, DARKGRAY, LIGHTGRAY, SKYBLUE};
+use crate::charts::{species_color, SimHistory};
+use crate::grid::Grid;
+use crate::organism_manager::SimData;
+use crate::utils::{WIDTH, HEIGHT};
+use crate::Direction;
+
+const GLYPH_WIDTH: usize = 3;
+const GLYPH_HEIGHT: usize = 5;
+
+/// An RGBA image drawn on the CPU, so frames can be captured without a window or a GPU.
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: usize, height: usize, background: Color) -> Self {
+        Canvas { width, height, pixels: rgba(background).repeat(width * height) }
+    }
+
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Fills a rectangle, rounding its edges to whole pixels and clipping it to the canvas.
+    pub fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let clip = |value: f32, max: usize| (value.round().max(0.0) as usize).min(max);
+        let (left, right) = (clip(x, self.width), clip(x + width, self.width));
+        let (top, bottom) = (clip(y, self.height), clip(y + height, self.height));
+        let color = rgba(color);
+        for row in top..bottom {
+            for column in left..right {
+                let index = (row * self.width + column) * 4;
+                self.pixels[index..index + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    /// Copies another canvas onto this one with its top left corner at (x, y).
+    pub fn blit(&mut self, other: &Canvas, x: usize, y: usize) {
+        for row in 0..other.height.min(self.height.saturating_sub(y)) {
+            let width = other.width.min(self.width.saturating_sub(x));
+            let from = row * other.width * 4;
+            let to = ((y + row) * self.width + x) * 4;
+            self.pixels[to..to + width * 4].copy_from_slice(&other.pixels[from..from + width * 4]);
+        }
+    }
+
+    /// Writes text in a built-in 3×5 pixel font, each font pixel `size` pixels wide. Letters are drawn
+    /// upper case and characters the font lacks are left blank.
+    pub fn text(&mut self, text: &str, x: f32, y: f32, size: f32, color: Color) {
+        for (i, character) in text.chars().enumerate() {
+            let glyph_x = x + i as f32 * (GLYPH_WIDTH + 1) as f32 * size;
+            for (row, bits) in glyph(character).iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill_rect(glyph_x + column as f32 * size, y + row as f32 * size, size, size, color);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn save_png(&self, path: &Path) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(path)?), self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&self.pixels)?;
+        Ok(())
+    }
+}
+
+fn rgba(color: Color) -> [u8; 4] {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(color.r), channel(color.g), channel(color.b), channel(color.a)]
+}
+
+/// Rows of a glyph in the 3×5 font, most significant of the three bits on the left.
+fn glyph(character: char) -> [u8; GLYPH_HEIGHT] {
+    match character.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '/' => [0b001, 0b001, 0b010, 0b100, 0b100],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '#' => [0b101, 0b111, 0b101, 0b111, 0b101],
+        _ => [0; GLYPH_HEIGHT],
+    }
+}
+
+/// Draws every tile of the world `scale` pixels square, coloured as `Grid::draw` colours them.
+pub fn rasterise_world(grid: &Grid, organism_colors: &HashMap<usize, Color>, scale: usize) -> Canvas {
+    let mut canvas = Canvas::new(WIDTH * scale, HEIGHT * scale, DARKGRAY);
+    let tile = scale as f32;
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let (color, eye) = grid.tile_color(x, y, organism_colors);
+            let (left, top) = (x as f32 * tile, y as f32 * tile);
+            canvas.fill_rect(left, top, tile, tile, color);
+            if eye != Direction::None {
+                // a bar from the centre to the edge the eye faces, 0.4 tiles thick like the on-screen line
+                let span = |start: f32, offset: f32| match offset {
+                    offset if offset < 0.0 => (start, tile * 0.5),
+                    offset if offset > 0.0 => (start + tile * 0.5, tile * 0.5),
+                    _ => (start + tile * 0.3, tile * 0.4),
+                };
+                let ((bar_x, bar_width), (bar_y, bar_height)) = (span(left, eye.x_offset()), span(top, eye.y_offset()));
+                canvas.fill_rect(bar_x, bar_y, bar_width, bar_height, BLACK);
+            }
+        }
+    }
+    canvas
+}
+
+/// A side panel with the sim statistics, the species abundance in `stats` and the population history,
+/// `width` pixels wide and as tall as a world raster at the same scale.
+pub fn rasterise_menu(stats: &SimData, history: &SimHistory, width: usize, scale: usize) -> Canvas {
+    let height = HEIGHT * scale;
+    let mut canvas = Canvas::new(width, height, LIGHTGRAY);
+    let size = (scale / 2).max(1) as f32;
+    let line_height = (GLYPH_HEIGHT + 2) as f32 * size;
+    let margin = 2.0 * size;
+
+    let lines = [
+        format!("Tick {}", stats.frame),
+        format!("Organisms {}", stats.organism_num),
+        format!("Food {}", stats.food_amount),
+        format!("Mean size {:.1}", stats.mean_size),
+        format!("Deaths H/A/P {}/{}/{}", stats.hunger_death, stats.age_death, stats.predation_death),
+        format!("Mutation {:.3}", stats.mean_mutation_rate),
+    ];
+    let mut y = margin;
+    for line in lines.iter() {
+        canvas.text(line, margin, y, size, BLACK);
+        y += line_height;
+    }
+
+    // one bar per species in `species_counts`, as long as its share of the population
+    y += line_height * 0.5;
+    canvas.text("Species", margin, y, size, BLACK);
+    y += line_height;
+    let bar_width = width as f32 - 2.0 * margin;
+    for (species, count) in stats.species_counts.iter() {
+        let share = *count as f32 / stats.organism_num.max(1) as f32;
+        canvas.fill_rect(margin, y, bar_width * share, line_height - size, species_color(*species));
+        canvas.text(&count.to_string(), margin + size, y + size * 0.5, size, BLACK);
+        y += line_height;
+    }
+
+    // population history as filled columns, oldest on the left, using the rest of the panel
+    y += line_height * 0.5;
+    canvas.text("Population", margin, y, size, BLACK);
+    y += line_height;
+    let chart_height = height as f32 - y - margin;
+    if chart_height <= 0.0 {
+        return canvas;
+    }
+    canvas.fill_rect(margin, y, bar_width, chart_height, DARKGRAY);
+    let samples = history.samples();
+    let max = samples.iter().map(|sample| sample.organism_num).max().unwrap_or(0).max(1) as f32;
+    let column_width = bar_width / samples.len().max(1) as f32;
+    for (i, sample) in samples.iter().enumerate() {
+        let column_height = sample.organism_num as f32 / max * chart_height;
+        canvas.fill_rect(margin + i as f32 * column_width, y + chart_height - column_height, column_width.max(1.0), column_height, SKYBLUE);
+    }
+    canvas
+}
@@ -0,0 +1,94 @@
+use crate::utils::MAX_EYE_DIST;
+
+pub const INTERNAL_INPUT_NUM: usize = 4; // satiety, energy, remaining lifetime, bias
+pub const EYE_INPUT_NUM: usize = 7; // proximity followed by one channel per `Seen` kind
+
+/// What an eye ray stopped at.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Seen {
+    Nothing,
+    Food,
+    Wall,
+    Plant, // producers without movers
+    Killer,
+    Kin, // same species as the viewer
+    Organism,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EyeReading {
+    pub seen: Seen,
+    pub distance: usize, // tiles travelled by the ray, MAX_EYE_DIST if nothing was seen
+}
+
+impl EyeReading {
+    /// Proximity is 1 right next to the eye and falls to 0 at the end of its range, the remaining channels are one-hot.
+    pub fn to_inputs(self) -> [f32; EYE_INPUT_NUM] {
+        let mut inputs = [0.0; EYE_INPUT_NUM];
+        inputs[0] = if self.seen == Seen::Nothing {
+            0.0
+        } else {
+            1.0 - (self.distance.saturating_sub(1) as f32 / MAX_EYE_DIST as f32)
+        };
+
+        let channel = match self.seen {
+            Seen::Nothing => None,
+            Seen::Food => Some(1),
+            Seen::Wall => Some(2),
+            Seen::Plant => Some(3),
+            Seen::Killer => Some(4),
+            Seen::Kin => Some(5),
+            Seen::Organism => Some(6),
+        };
+        if let Some(channel) = channel {
+            inputs[channel] = 1.0;
+        }
+        inputs
+    }
+}
+
+/// Internal state inputs, each normalised to 0..=1.
+pub fn internal_inputs(satiety: f32, energy_fraction: f32, lifetime_fraction: f32) -> [f32; INTERNAL_INPUT_NUM] {
+    [
+        satiety.clamp(0.0, 1.0),
+        energy_fraction.clamp(0.0, 1.0),
+        lifetime_fraction.clamp(0.0, 1.0),
+        1.0,
+    ]
+}
+
+/// Identifies a brain input independently of its position in the sensor vector. Eye inputs are keyed by
+/// the eye's genome frame offset, so an eye keeps its input neurons when other eyes are gained or lost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputKey {
+    Internal(usize),
+    Eye { x: i32, y: i32, channel: usize },
+}
+
+impl InputKey {
+    /// `internal:<index>` or `eye:<x>:<y>:<channel>`, as stored in world snapshots.
+    pub fn encode(&self) -> String {
+        match self {
+            InputKey::Internal(index) => format!("internal:{}", index),
+            InputKey::Eye { x, y, channel } => format!("eye:{}:{}:{}", x, y, channel),
+        }
+    }
+
+    pub fn decode(encoded: &str) -> Option<InputKey> {
+        let parts: Vec<&str> = encoded.split(':').collect();
+        match parts.as_slice() {
+            ["internal", index] => Some(InputKey::Internal(index.parse().ok()?)),
+            ["eye", x, y, channel] => Some(InputKey::Eye { x: x.parse().ok()?, y: y.parse().ok()?, channel: channel.parse().ok()? }),
+            _ => None,
+        }
+    }
+}
+
+/// Keys in the same order the organism writes its sensor vector: internal inputs, then each eye's channels.
+pub fn input_keys(eyes: &[(i32, i32)]) -> Vec<InputKey> {
+    let mut keys: Vec<InputKey> = (0..INTERNAL_INPUT_NUM).map(InputKey::Internal).collect();
+    for &(x, y) in eyes {
+        keys.extend((0..EYE_INPUT_NUM).map(|channel| InputKey::Eye { x, y, channel }));
+    }
+    keys
+}
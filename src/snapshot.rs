@@ -0,0 +1,184 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+use crate::brain::Brain;
+use crate::gallery::SpeciesRegistry;
+use crate::grid::Grid;
+use crate::organism::Organism;
+use crate::organism_manager::{OrganismManager, SimData};
+use crate::params;
+use crate::utils::CHART_SPECIES;
+
+const SNAPSHOT_FORMAT: u32 = 1; // raise whenever the lines written below change, older files are then refused
+
+impl OrganismManager {
+    /// Writes the whole world: the settings and counters as `key = value` lines, so the file can also be passed
+    /// as `--config`, then tab separated lines for the tiles, the innovation history, every species ever seen
+    /// and every living organism with its brain. Charts and heatmaps start over when the world is loaded.
+    pub fn save_world(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let stats = self.current_stats();
+        let mut text = format!(
+            "# Organisms snapshot\nformat = {}\nseed = {}\nticks = {}\nnext_id = {}\n\
+             hunger_deaths = {}\nage_deaths = {}\npredation_deaths = {}\nblocked_births = {}\nedge_births = {}\nextinctions = {}\n",
+            SNAPSHOT_FORMAT, self.seed, self.frame, self.next_id(),
+            stats.hunger_death, stats.age_death, stats.predation_death, stats.blocked_births, stats.out_of_bounds_births, stats.extinctions,
+        );
+        text.push_str(&params::config_text(&self.params, &self.mutation_policy));
+        text.push_str(&self.grid.encode());
+        text.push_str(&self.innovations.encode());
+        text.push_str(&self.species().encode());
+        for organism in self.organisms.iter() {
+            text.push_str(&organism.encode_state());
+        }
+        fs::write(path, text)
+    }
+
+    /// Reads a world written by `save_world`. Its random streams are restarted from the saved seed and tick.
+    pub fn load_world(path: impl AsRef<Path>) -> io::Result<OrganismManager> {
+        let text = fs::read_to_string(path)?;
+        let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+        let value = |key: &str| text.lines().filter_map(params::config_line).find(|(line_key, _)| *line_key == key).map(|(_, value)| value);
+        let number = |key: &str| value(key).and_then(|value| value.parse::<u128>().ok()).ok_or_else(|| invalid(format!("the snapshot has no valid {}", key)));
+
+        match value("format").map(|format| format.parse::<u32>()) {
+            Some(Ok(SNAPSHOT_FORMAT)) => {}
+            Some(Ok(format)) => return Err(invalid(format!("snapshot format {} is not supported, this version reads format {}", format, SNAPSHOT_FORMAT))),
+            _ => return Err(invalid("not a world snapshot, or one from before the world state was saved".to_owned())),
+        }
+
+        let mut organism_manager = OrganismManager::with_seed(number("seed")? as u64);
+        organism_manager.frame = number("ticks")?;
+        params::parse_config(&text, &mut organism_manager.params, &mut organism_manager.mutation_policy);
+        let mut counters = SimData::new();
+        counters.hunger_death = number("hunger_deaths")? as usize;
+        counters.age_death = number("age_deaths")? as usize;
+        counters.predation_death = number("predation_deaths")? as usize;
+        counters.blocked_births = number("blocked_births")? as usize;
+        counters.out_of_bounds_births = number("edge_births")? as usize;
+        counters.extinctions = number("extinctions")? as usize;
+
+        let mut species = SpeciesRegistry::new();
+        let mut organisms: Vec<Organism> = Vec::new();
+        for (line_number, line) in text.lines().enumerate() {
+            let fields: Vec<&str> = line.split('\t').collect();
+            if fields.len() < 2 {
+                continue; // settings, counters and comments
+            }
+            let decoded = match fields[0] {
+                "tiles" | "kill" => organism_manager.grid.decode_line(&fields),
+                "tracker" | "fixed" | "innovation" | "split" => organism_manager.innovations.decode_line(&fields),
+                "species" => species.decode_line(&fields),
+                "organism" => Organism::decode_state(&fields).map(|organism| organisms.push(organism)),
+                "brain" => Brain::decode(&fields).and_then(|brain| organisms.last_mut().map(|organism| organism.brain = Some(brain))),
+                "node" | "link" => organisms.last_mut().and_then(|organism| organism.brain.as_mut()).and_then(|brain| brain.decode_line(&fields)),
+                _ => None,
+            };
+            if decoded.is_none() {
+                return Err(invalid(format!("line {}: could not read `{}` line", line_number + 1, fields[0])));
+            }
+        }
+        if let Some(organism) = organisms.iter().find(|organism| organism.cells.iter().any(|(dx, dy, _)| !Grid::in_bounds(organism.x as i32 + dx, organism.y as i32 + dy))) {
+            return Err(invalid(format!("organism {} lies outside the world", organism.id)));
+        }
+
+        let next_id = number("next_id")? as usize;
+        organism_manager.restore(organisms, species, counters, next_id);
+        let seed = organism_manager.seed;
+        organism_manager.reseed(seed);
+        Ok(organism_manager)
+    }
+
+    /// Plain text summary of the world: the counters in `SimData` and the most populous living species.
+    pub fn stats_report(&self) -> String {
+        let stats = self.current_stats();
+        let mut report = format!(
+            "Tick: {}\nOrganisms: {}\nFood: {}\nMean size: {:.2}\nMean mutation rate: {:.3}\n\
+             Deaths: {} hunger, {} age, {} predation\nFailed births: {} blocked, {} edge\nExtinctions: {}\n",
+            stats.frame, stats.organism_num, stats.food_amount, stats.mean_size, stats.mean_mutation_rate,
+            stats.hunger_death, stats.age_death, stats.predation_death,
+            stats.blocked_births, stats.out_of_bounds_births, stats.extinctions,
+        );
+
+        report.push_str(&format!("\nTop species (of {}):\n", SpeciesRegistry::living_counts(&self.organisms).len()));
+        report.push_str("species   living  births  since  cells\n");
+        for (species, living) in stats.species_counts.iter().take(CHART_SPECIES) {
+            let Some(record) = self.species().get(*species) else {
+                continue;
+            };
+            report.push_str(&format!("{:08x}  {:>6}  {:>6}  {:>5}  {:>5}\n", *species as u32, living, record.births, record.first_seen, record.anatomy.len()));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::brain::PlasticityMode;
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("organisms-{}-{}.txt", name, std::process::id()))
+    }
+
+    /// Whole worlds keep their tile arrays inline, more than the default stack of a test thread holds.
+    fn on_main_sized_stack(test: impl FnOnce() + Send + 'static) {
+        std::thread::Builder::new().stack_size(32 << 20).spawn(test).unwrap().join().unwrap();
+    }
+
+    fn run(seed: u64, ticks: u128) -> OrganismManager {
+        let mut world = OrganismManager::with_seed(seed);
+        world.mutation_policy.plasticity = PlasticityMode::Lamarckian;
+        world.init();
+        while world.frame < ticks {
+            world.tick();
+        }
+        world
+    }
+
+    #[test]
+    fn loaded_world_saves_the_same_file() {
+        on_main_sized_stack(|| {
+            let (first, second) = (temp_path("first"), temp_path("second"));
+            let world = run(7, 300);
+            world.save_world(&first).unwrap();
+            let loaded = OrganismManager::load_world(&first).unwrap();
+            loaded.save_world(&second).unwrap();
+
+            assert_eq!(loaded.frame, world.frame);
+            assert_eq!(loaded.organisms.len(), world.organisms.len());
+            assert_eq!(loaded.next_id(), world.next_id());
+            assert_eq!(loaded.mutation_policy.plasticity, PlasticityMode::Lamarckian);
+            assert_eq!(fs::read_to_string(&first).unwrap(), fs::read_to_string(&second).unwrap());
+            let _ = (fs::remove_file(first), fs::remove_file(second));
+        });
+    }
+
+    #[test]
+    fn loaded_worlds_run_on_alike() {
+        on_main_sized_stack(|| {
+            let path = temp_path("replay");
+            run(8, 200).save_world(&path).unwrap();
+            let mut worlds = [OrganismManager::load_world(&path).unwrap(), OrganismManager::load_world(&path).unwrap()];
+            for world in worlds.iter_mut() {
+                for _ in 0..100 {
+                    world.tick();
+                }
+            }
+            let [a, b] = worlds;
+            assert_eq!(a.current_stats().organism_num, b.current_stats().organism_num);
+            assert!(a.organisms.iter().zip(b.organisms.iter()).all(|(a, b)| (a.id, a.x, a.y) == (b.id, b.x, b.y)));
+            let _ = fs::remove_file(path);
+        });
+    }
+
+    #[test]
+    fn load_world_refuses_other_formats() {
+        let path = temp_path("format");
+        fs::write(&path, "format = 2\nseed = 1\n").unwrap();
+        assert!(OrganismManager::load_world(&path).is_err());
+        fs::write(&path, "producer_rate = 0.1\n").unwrap();
+        assert!(OrganismManager::load_world(&path).is_err());
+        let _ = fs::remove_file(path);
+    }
+}
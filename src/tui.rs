@@ -0,0 +1,132 @@
+use std::collections::HashMap;
+use std::io::{self, BufWriter, Stdout, Write};
+use std::time::{Duration, Instant};
+use crossterm::cursor;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::style::{self, Print, SetBackgroundColor, SetForegroundColor};
+use crossterm::terminal::{self, ClearType};
+use crossterm::{execute, queue};
+use macroquad::color::{Color, BLACK};
+use crate::controls::SimControls;
+use crate::grid::Grid;
+use crate::organism_manager::OrganismManager;
+use crate::utils::{WIDTH, HEIGHT};
+
+const FRAME_TIME: Duration = Duration::from_millis(50);
+const HEADER_LINES: u16 = 2;
+
+/// Raw mode and the alternate screen for as long as it lives, so the shell is restored however the loop ends.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = execute!(io::stdout(), style::ResetColor, cursor::Show, terminal::LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the simulation in the terminal until Q, Esc or Ctrl+C, starting paused if asked. Space pauses,
+/// N (or the right arrow) steps and +/- change the speed, as in the window.
+pub fn run(organism_manager: &mut OrganismManager, paused: bool) -> io::Result<()> {
+    let _guard = TerminalGuard::enter()?;
+    let mut out = BufWriter::new(io::stdout());
+    let mut controls = SimControls::new();
+    controls.paused = paused;
+
+    loop {
+        let frame_start = Instant::now();
+        controls.run_frame(true, || organism_manager.tick());
+        draw(&mut out, organism_manager, &controls)?;
+
+        // handle keys while waiting out the rest of the frame
+        while let Some(remaining) = FRAME_TIME.checked_sub(frame_start.elapsed()) {
+            if !event::poll(remaining)? {
+                break;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                KeyCode::Char(' ') => controls.paused = !controls.paused,
+                KeyCode::Char('n') | KeyCode::Right => controls.step(),
+                KeyCode::Char('+') | KeyCode::Char('=') => controls.faster(),
+                KeyCode::Char('-') => controls.slower(),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Draws the counters on the first two lines and the world under them. Each character shows two blocks of
+/// tiles, the upper one as the foreground of '▀' and the lower one as its background. A block is a single tile
+/// unless the world has to be shrunk to fit the terminal.
+fn draw(out: &mut BufWriter<Stdout>, organism_manager: &OrganismManager, controls: &SimControls) -> io::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let world_rows = rows.saturating_sub(HEADER_LINES).max(1) as usize;
+    let block = WIDTH.div_ceil(columns.max(1) as usize).max(HEIGHT.div_ceil(world_rows * 2));
+
+    let stats = organism_manager.current_stats();
+    let status = if controls.paused { "paused".to_owned() } else { format!("{}x", controls.speed) };
+    let header = [
+        format!("Tick {}  Organisms {}  Food {}  Deaths: hunger {} age {} predation {}",
+            stats.frame, stats.organism_num, stats.food_amount, stats.hunger_death, stats.age_death, stats.predation_death),
+        format!("Speed {}  TPS {:.0}  Scale 1:{}  [Space] pause  [N] step  [+/-] speed  [Q] quit",
+            status, controls.ticks_per_second(), block),
+    ];
+    queue!(out, style::ResetColor)?;
+    for (line, text) in header.iter().enumerate() {
+        let text: String = text.chars().take(columns as usize).collect();
+        queue!(out, cursor::MoveTo(0, line as u16), Print(text), terminal::Clear(ClearType::UntilNewLine))?;
+    }
+
+    let colors = organism_manager.organism_colors();
+    let grid = &organism_manager.grid;
+    for row in 0..HEIGHT.div_ceil(2 * block).min(world_rows) {
+        queue!(out, cursor::MoveTo(0, HEADER_LINES + row as u16))?;
+        let mut current = None;
+        for column in 0..WIDTH.div_ceil(block) {
+            let x = column * block;
+            let top = block_color(grid, &colors, x, 2 * row * block, block).unwrap_or(BLACK);
+            let bottom = block_color(grid, &colors, x, (2 * row + 1) * block, block).unwrap_or(BLACK);
+            let pair = (terminal_color(top), terminal_color(bottom));
+            if current != Some(pair) {
+                queue!(out, SetForegroundColor(pair.0), SetBackgroundColor(pair.1))?;
+                current = Some(pair);
+            }
+            queue!(out, Print('▀'))?;
+        }
+        queue!(out, style::ResetColor, terminal::Clear(ClearType::UntilNewLine))?;
+    }
+    queue!(out, terminal::Clear(ClearType::FromCursorDown))?;
+    out.flush()
+}
+
+/// Colour of the most telling tile in a block of tiles: an organism over a wall over food over empty ground.
+/// None if the block lies outside the world.
+fn block_color(grid: &Grid, organism_colors: &HashMap<usize, Color>, x: usize, y: usize, size: usize) -> Option<Color> {
+    let rank = |(x, y): (usize, usize)| {
+        if grid.occupants[y][x].is_some() { 3 } else if grid.walls[y][x] { 2 } else if grid.foods[y][x] { 1 } else { 0 }
+    };
+    (y..(y + size).min(HEIGHT))
+        .flat_map(|tile_y| (x..(x + size).min(WIDTH)).map(move |tile_x| (tile_x, tile_y)))
+        .max_by_key(|tile| rank(*tile))
+        .map(|(x, y)| grid.tile_color(x, y, organism_colors).0)
+}
+
+fn terminal_color(color: Color) -> style::Color {
+    let channel = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    style::Color::Rgb { r: channel(color.r), g: channel(color.g), b: channel(color.b) }
+}
@@ -1,9 +1,6 @@
-use rand::SeedableRng;
-use rand::rngs::StdRng;
-use std::fs::File;
-use std::io::Write;
+use crate::mutation::MutationPolicy;
 use crate::organism_manager::OrganismManager;
-use crate::utils::*;
+use crate::params::SimParams;
 
 // Define hyperparameter search space
 const PRODUCER_RATES: [f32; 3] = [0.05, 0.07, 0.1];
@@ -11,49 +8,40 @@ const MUTATION_RATES: [f32; 3] = [0.4, 0.6, 0.8];
 const FOOD_BENEFITS: [f32; 3] = [0.15, 0.18, 0.25];
 const LIFETIME_MULTIPLIERS: [i32; 3] = [28, 32, 40];
 const REPRODUCTION_MULTIPLIERS: [f32; 3] = [1.5, 1.85, 2.2];
+const TUNING_TICKS: usize = 500;
 
 struct SimResult {
-    producer_rate: f32,
-    mutation_rate: f32,
-    food_benefit: f32,
-    lifetime_multiplier: i32,
-    reproduction_multiplier: f32,
+    params: SimParams,
     avg_population: f32,
     extinction_rate: f32,
     growth_stability: f32,
 }
 
-fn run_simulation(seed: u64, producer_rate: f32, mutation_rate: f32, food_benefit: f32, 
-                  lifetime_multiplier: i32, reproduction_multiplier: f32) -> SimResult {
-    
+impl SimResult {
+    /// Best balance: High avg_population, Low extinction rate, Smooth growth
+    fn score(&self) -> f32 {
+        self.avg_population * 10.0 - self.extinction_rate * 20.0 - self.growth_stability * 5.0
+    }
+}
+
+fn run_simulation(seed: u64, params: SimParams, policy: &MutationPolicy) -> SimResult {
     println!("Running simulation with seed: {}, producer_rate: {}, mutation_rate: {}, food_benefit: {}, lifetime_multiplier: {}, reproduction_multiplier: {}",
-             seed, producer_rate, mutation_rate, food_benefit, lifetime_multiplier, reproduction_multiplier);
+             seed, params.producer_rate, params.mutation_rate, params.food_benefit, params.lifetime_multiplier, params.reproduction_energy_multiplier);
 
-    let mut rng = StdRng::seed_from_u64(seed);
-    let mut organism_manager = OrganismManager::new();
+    let mut organism_manager = OrganismManager::with_seed(seed);
+    organism_manager.params = params;
+    organism_manager.mutation_policy = policy.clone();
     organism_manager.init();
 
     let mut population_history = Vec::new();
-    let mut extinction_count = 0;
-    let mut last_population = 10;
-
-    for generation in 0..500 {  // Run for 500 generations
-        organism_manager.update();
-        let pop = organism_manager.organisms.len() as f32;
-        population_history.push(pop);
-
-        if pop == 0.0 {
-            extinction_count += 1;
-            organism_manager.init(); // Restart if extinction occurs
-            println!("Extinction occurred at generation {}. Restarting...", generation);
-        }
-        
-        last_population = pop as usize;
+    for _ in 0..TUNING_TICKS {
+        organism_manager.tick(); // an extinct world is reseeded by the tick itself and counted in `extinctions`
+        population_history.push(organism_manager.organisms.len() as f32);
     }
 
     // Compute metrics
     let avg_population = population_history.iter().sum::<f32>() / population_history.len() as f32;
-    let extinction_rate = extinction_count as f32 / 500.0; // % of frames where pop hit zero
+    let extinction_rate = organism_manager.current_stats().extinctions as f32 / TUNING_TICKS as f32;
     let growth_stability = population_history.windows(2)
         .map(|w| (w[1] - w[0]).abs())
         .sum::<f32>() / population_history.len() as f32; // Smoother = better
@@ -62,55 +50,44 @@ fn run_simulation(seed: u64, producer_rate: f32, mutation_rate: f32, food_benefi
              avg_population, extinction_rate, growth_stability);
 
     SimResult {
-        producer_rate,
-        mutation_rate,
-        food_benefit,
-        lifetime_multiplier,
-        reproduction_multiplier,
+        params: organism_manager.params,
         avg_population,
         extinction_rate,
         growth_stability,
     }
 }
 
-pub fn main() {
+/// Grid search over the search space above, every run starting from `seed`. Settings outside the search space
+/// are taken from `base`. Returns the best scoring settings.
+pub fn tune(base: &SimParams, policy: &MutationPolicy, seed: u64) -> SimParams {
     let mut results = Vec::new();
-    
+
     for &producer_rate in &PRODUCER_RATES {
         for &mutation_rate in &MUTATION_RATES {
             for &food_benefit in &FOOD_BENEFITS {
                 for &lifetime_multiplier in &LIFETIME_MULTIPLIERS {
                     for &reproduction_multiplier in &REPRODUCTION_MULTIPLIERS {
-                        let result = run_simulation(
-                            42, // Fixed seed for consistency
-                            producer_rate, mutation_rate, food_benefit, 
-                            lifetime_multiplier, reproduction_multiplier
-                        );
-                        results.push(result);
+                        let params = SimParams {
+                            producer_rate,
+                            mutation_rate,
+                            food_benefit,
+                            lifetime_multiplier,
+                            reproduction_energy_multiplier: reproduction_multiplier,
+                            ..base.clone()
+                        };
+                        results.push(run_simulation(seed, params, policy));
                     }
                 }
             }
         }
     }
 
-    // Sort by best balance: High avg_population, Low extinction rate, Smooth growth
-    results.sort_by(|a, b| 
-        ((b.avg_population * 10.0 - b.extinction_rate * 20.0 - b.growth_stability * 5.0) 
-        .partial_cmp(&(a.avg_population * 10.0 - a.extinction_rate * 20.0 - a.growth_stability * 5.0)))
-        .unwrap()
-    );
-
+    results.sort_by(|a, b| b.score().total_cmp(&a.score()));
     let best_result = &results[0];
 
     println!("Best result - producer_rate: {}, mutation_rate: {}, food_benefit: {}, lifetime_multiplier: {}, reproduction_multiplier: {}, avg_population: {}, extinction_rate: {}, growth_stability: {}",
-             best_result.producer_rate, best_result.mutation_rate, best_result.food_benefit, best_result.lifetime_multiplier, best_result.reproduction_multiplier, best_result.avg_population, best_result.extinction_rate, best_result.growth_stability);
+             best_result.params.producer_rate, best_result.params.mutation_rate, best_result.params.food_benefit, best_result.params.lifetime_multiplier,
+             best_result.params.reproduction_energy_multiplier, best_result.avg_population, best_result.extinction_rate, best_result.growth_stability);
 
-    // Save best hyperparameters to a file
-    let mut file = File::create("best_hyperparams.txt").expect("Failed to create file");
-    writeln!(file, "Best Hyperparameters:").unwrap();
-    writeln!(file, "Producer Rate: {}", best_result.producer_rate).unwrap();
-    writeln!(file, "Mutation Rate: {}", best_result.mutation_rate).unwrap();
-    writeln!(file, "Food Benefit: {}", best_result.food_benefit).unwrap();
-    writeln!(file, "Lifetime Multiplier: {}", best_result.lifetime_multiplier).unwrap();
-    writeln!(file, "Reproduction Multiplier: {}", best_result.reproduction_multiplier).unwrap();
+    best_result.params.clone()
 }
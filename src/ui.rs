@@ -0,0 +1,55 @@
+use macroquad::prelude::*;
+use crate::utils::SLIDER_LABEL_SHARE;
+
+pub fn contains(x: f32, y: f32, width: f32, height: f32, point_x: f32, point_y: f32) -> bool {
+    point_x >= x && point_x <= x + width && point_y >= y && point_y <= y + height
+}
+
+/// Draws a labelled button and reports whether it was clicked this frame. Highlighted buttons are drawn lighter.
+pub fn button(x: f32, y: f32, width: f32, height: f32, label: &str, highlighted: bool) -> bool {
+    draw_rectangle(x, y, width, height, if highlighted { WHITE } else { GRAY });
+    draw_text(label, x + 6.0, y + height / 2.0 + 6.0, 20.0, BLACK);
+    if !is_mouse_button_pressed(MouseButton::Left) {
+        return false;
+    }
+    let (mouse_x, mouse_y) = mouse_position();
+    contains(x, y, width, height, mouse_x, mouse_y)
+}
+
+/// Draws a labelled horizontal slider. Clicking or dragging on the bar sets the value and the mouse wheel nudges it.
+/// Returns the new value if it changed this frame.
+pub fn slider(area: Rect, label: &str, value: f32, (min, max): (f32, f32), integer: bool) -> Option<f32> {
+    let Rect { x, y, w: width, h: height } = area;
+    let label_width = width * SLIDER_LABEL_SHARE;
+    let bar_x = x + label_width;
+    let bar_width = width - label_width;
+    let text = if integer { format!("{} {:.0}", label, value) } else { format!("{} {:.3}", label, value) };
+    draw_text(text.as_str(), x, y + height - 5.0, 16.0, WHITE);
+    draw_rectangle(bar_x, y + 3.0, bar_width, height - 6.0, GRAY);
+    let fraction = ((value - min) / (max - min)).clamp(0.0, 1.0);
+    draw_rectangle(bar_x, y + 3.0, bar_width * fraction, height - 6.0, SKYBLUE);
+
+    let (mouse_x, mouse_y) = mouse_position();
+    if !contains(bar_x, y, bar_width, height, mouse_x, mouse_y) {
+        return None;
+    }
+    let mut new_value = value;
+    if is_mouse_button_down(MouseButton::Left) {
+        new_value = min + (mouse_x - bar_x) / bar_width * (max - min);
+    }
+    let wheel = mouse_wheel().1;
+    if wheel != 0.0 {
+        let step = if integer { 1.0 } else { (max - min) / 100.0 };
+        new_value = value + step * wheel.signum();
+    }
+    new_value = new_value.clamp(min, max);
+    if integer {
+        new_value = new_value.round();
+    }
+    (new_value != value).then_some(new_value)
+}
+
+/// Whether an undrawn clickable area was clicked this frame.
+pub fn button_area(area: Rect) -> bool {
+    is_mouse_button_pressed(MouseButton::Left) && area.contains(mouse_position().into())
+}
@@ -1,23 +1,59 @@
-#[cfg(feature = "tuning")]
-pub use crate::tuner::Constants; // Use tuner constants if tuning is enabled
+use crate::brain::PlasticityMode;
 
 pub const WIDTH: usize = 140;
 pub const HEIGHT: usize = 90;
 pub const CELL_SIZE: f32 = 10.0;
 
 pub const MENU_WIDTH: f32 = 300.0;
-pub const MENU_HEIGHT: f32 = 600.0;
+pub const MAX_ZOOM: f32 = 60.0; // largest on-screen tile size in pixels
 
 pub const GRAPHICS: bool = true;
 
 pub const SEED: u64 = 1;
+pub const HISTORY_LEN: usize = 280; // samples kept for the charts, about one per pixel of the panel
+pub const HISTORY_INTERVAL: u128 = 10; // ticks between history samples
+pub const CHART_SPECIES: usize = 6; // species drawn separately in the abundance chart
+pub const GALLERY_SPECIES: usize = 6; // species shown in the menu gallery
+pub const CONFIG_FILE: &str = "organisms.cfg"; // written by the settings panel
+pub const SLIDER_LABEL_SHARE: f32 = 0.55; // part of a settings slider taken by its label, the rest is the bar
+pub const GENOME_LIBRARY_FILE: &str = "genomes.txt"; // designs saved from the organism designer
+pub const TUNED_CONFIG_FILE: &str = "tuned.cfg"; // written by the tune command unless --output says otherwise
+pub const MAX_TICKS_PER_FRAME: usize = 256; // fast-forward cap while graphics are on
+pub const FAST_FORWARD_BUDGET: f64 = 0.012; // most seconds of ticking per frame, and all of them while graphics are off
+pub const CAPTURE_DIR: &str = "captures"; // where snapshots and recordings are written
+pub const CAPTURE_SCALE: usize = 4; // pixels per tile in captured images
+pub const CAPTURE_INTERVAL: u128 = 50; // ticks between recorded frames
+pub const CAPTURE_MENU_TILES: usize = 50; // width of the captured menu panel, in tiles
+pub const GIF_FRAME_DELAY: u16 = 8; // hundredths of a second each time-lapse frame is shown
 
 pub const PRODUCER_RATE: f32 = 0.07;
 pub const LIFETIME_MULTIPLIER: i32 = 32;
-pub const MUTATION_RATE: f32 = 0.6;
-pub const HIDDEN_NEURON_NUM: usize = 3;
+pub const MUTATION_RATE: f32 = 0.6; // initial mean number of anatomy mutations per birth, evolves per genome
+pub const CHANGE_CELL_WEIGHT: f32 = 1.0;
+pub const REMOVE_CELL_WEIGHT: f32 = 1.0;
+pub const ADD_CELL_WEIGHT: f32 = 1.0;
+pub const SWAP_CELL_WEIGHT: f32 = 0.5;
+pub const DUPLICATE_PART_WEIGHT: f32 = 0.25;
+pub const WEIGHT_MUTATION_PROB: f32 = 0.2;
+pub const WEIGHT_MUTATION_STD: f32 = 0.3;
+pub const MUTATION_RATE_ADAPTATION: f32 = 0.2;
+pub const MIN_MUTATION_RATE: f32 = 0.01;
+pub const MAX_MUTATION_RATE: f32 = 5.0;
+pub const ADD_CONNECTION_PROB: f32 = 0.1;
+pub const ADD_NODE_PROB: f32 = 0.05;
+pub const DISABLE_CONNECTION_PROB: f32 = 0.03;
+pub const ACTIVATION_MUTATION_PROB: f32 = 0.05;
+pub const HIDDEN_NEURON_NUM: usize = 3; // hidden neurons allowed per brain cell
+pub const LEARNING_RATE: f32 = 0.05; // initial reward-modulated plasticity gene
+pub const HEBBIAN_RATE: f32 = 0.005; // initial plain co-activation plasticity gene
+pub const MAX_LEARNED_WEIGHT: f32 = 2.0; // cap on how far a weight can drift from its genetic value during life
+pub const DAMAGE_PENALTY: f32 = 1.0;
+pub const PLASTICITY: PlasticityMode = PlasticityMode::Baldwinian; // default of the plasticity setting
+pub const MEMORY_NEURON_NUM: usize = 2; // self-recurrent neurons every brain starts with, 0 gives a feed-forward brain
 pub const MAX_EYE_DIST: usize = 5;
+pub const MAX_SPEED: usize = 3; // most tiles a brained organism can move per tick, one per mover cell
 pub const FOOD_BENEFIT: f32 = 0.18;
 pub const HUNGER_RATE: f32 = 0.006;
 pub const DROP_FOOD_RATE: f32 = 0.1;
-pub const REPRODUCTION_ENEGRGY_MULTIPLER: f32 = 1.85;
\ No newline at end of file
+pub const REPRODUCTION_ENEGRGY_MULTIPLER: f32 = 1.85;
+pub const SPAWN_SEARCH_RADIUS: i32 = 6;
\ No newline at end of file